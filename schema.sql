@@ -2,7 +2,7 @@
 
 CREATE TABLE IF NOT EXISTS short_urls (
     id BIGSERIAL PRIMARY KEY,
-    short_code VARCHAR(32) UNIQUE,
+    short_code VARCHAR(32) NOT NULL UNIQUE,
     original_url TEXT NOT NULL,
     visit_count BIGINT NOT NULL DEFAULT 0,
     status SMALLINT NOT NULL DEFAULT 1,
@@ -28,3 +28,128 @@ BEGIN
         PERFORM setval('short_urls_id_seq', GREATEST((SELECT COALESCE(MAX(id), 0) FROM short_urls), 10000));
     END IF;
 END $$;
+
+-- Codes are now written together with the row. Older databases may still hold rows left without
+-- a code by the previous insert-then-update flow; nobody can have visited them, so they are dropped.
+DELETE FROM short_urls WHERE short_code IS NULL;
+ALTER TABLE short_urls ALTER COLUMN short_code SET NOT NULL;
+
+CREATE TABLE IF NOT EXISTS short_url_visits (
+    id BIGSERIAL PRIMARY KEY,
+    short_url_id BIGINT NOT NULL REFERENCES short_urls(id),
+    visited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    referrer TEXT,
+    user_agent TEXT,
+    accept_language TEXT,
+    ip_address VARCHAR(64)
+);
+
+CREATE INDEX IF NOT EXISTS idx_short_url_visits_url_time ON short_url_visits(short_url_id, visited_at);
+
+ALTER TABLE short_url_visits ADD COLUMN IF NOT EXISTS referrer_domain VARCHAR(255);
+ALTER TABLE short_url_visits ADD COLUMN IF NOT EXISTS browser VARCHAR(64);
+ALTER TABLE short_url_visits ADD COLUMN IF NOT EXISTS os VARCHAR(64);
+ALTER TABLE short_url_visits ADD COLUMN IF NOT EXISTS country CHAR(2);
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash CHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    username VARCHAR(64) NOT NULL UNIQUE,
+    role VARCHAR(16) NOT NULL DEFAULT 'user',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Keys without a user predate accounts and keep admin access.
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS user_id BIGINT REFERENCES users(id);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS owner_id BIGINT REFERENCES users(id);
+CREATE INDEX IF NOT EXISTS idx_short_urls_owner ON short_urls(owner_id);
+
+-- NULL scopes: everything the key's user role allows (admin for keys without a user).
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS scopes TEXT;
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ;
+
+-- Argon2 PHC string; NULL means the link is public.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS password_hash TEXT;
+
+-- NULL means unlimited; 1 makes a one-time link.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS max_visits BIGINT;
+
+-- Links with a future activates_at answer "not yet active", or redirect to prelaunch_url when set.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS activates_at TIMESTAMPTZ;
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS prelaunch_url TEXT;
+
+-- 301, 302, 307 or 308; NULL uses redirect.default_status from the config.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS redirect_status SMALLINT;
+
+-- Shown on the /{code}+ preview page.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS title VARCHAR(200);
+
+-- Opt-in passthrough of the incoming query string and of any path after /{code}/.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS forward_query BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS forward_path BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- UTM tags merged into the destination at redirect time; utm_override lets them replace existing params.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_source VARCHAR(200);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_medium VARCHAR(200);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_campaign VARCHAR(200);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_term VARCHAR(200);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_content VARCHAR(200);
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS utm_override BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Ordered device/platform targeting rules, evaluated before falling back to original_url.
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS targeting_rules JSONB;
+
+-- Weighted A/B destinations; a link with variants sends visitors to one of them instead of original_url.
+CREATE TABLE IF NOT EXISTS short_url_variants (
+    id BIGSERIAL PRIMARY KEY,
+    short_url_id BIGINT NOT NULL REFERENCES short_urls(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    weight INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_short_url_variants_url ON short_url_variants(short_url_id);
+
+ALTER TABLE short_urls ADD COLUMN IF NOT EXISTS sticky_variants BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE short_url_visits ADD COLUMN IF NOT EXISTS variant_id BIGINT;
+
+-- Future original_url changes, applied by the scheduler once applies_at has passed.
+CREATE TABLE IF NOT EXISTS short_url_schedules (
+    id BIGSERIAL PRIMARY KEY,
+    short_url_id BIGINT NOT NULL REFERENCES short_urls(id) ON DELETE CASCADE,
+    original_url TEXT NOT NULL,
+    applies_at TIMESTAMPTZ NOT NULL,
+    applied_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_short_url_schedules_url ON short_url_schedules(short_url_id, applies_at);
+CREATE INDEX IF NOT EXISTS idx_short_url_schedules_pending ON short_url_schedules(applies_at) WHERE applied_at IS NULL;
+
+-- Due schedules that a later due schedule of the same link replaced; applied_at is set on them too.
+ALTER TABLE short_url_schedules ADD COLUMN IF NOT EXISTS superseded BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Issuer that provisioned the user from a JWT; NULL for users created through the admin API.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS external_issuer TEXT;
+
+-- Links disabled through the admin API, which their owners cannot re-enable. Links that were already
+-- disabled when the column is added cannot be told apart, so they are treated as disabled by an admin.
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'short_urls' AND column_name = 'disabled_by_admin'
+    ) THEN
+        ALTER TABLE short_urls ADD COLUMN disabled_by_admin BOOLEAN NOT NULL DEFAULT FALSE;
+        UPDATE short_urls SET disabled_by_admin = TRUE WHERE status = 0;
+    END IF;
+END $$;
@@ -1,7 +1,65 @@
-use crate::services::short_url_service::ShortUrlService;
+use axum::http::{HeaderName, StatusCode};
+
+use crate::services::{
+    api_key_service::ApiKeyService, jwt_service::JwtService, rate_limiter::RateLimiter,
+    short_url_service::ShortUrlService,
+    user_service::UserService, visit_recorder::VisitRecorder, visit_service::VisitService,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub short_url_service: ShortUrlService,
+    pub visit_service: VisitService,
+    pub visit_recorder: VisitRecorder,
+    pub api_key_service: ApiKeyService,
+    pub user_service: UserService,
+    pub jwt_service: Option<JwtService>,
+    pub create_limiter: Option<RateLimiter>,
+    pub redirect_limiter: Option<RateLimiter>,
+    pub password_limiter: Option<RateLimiter>,
     pub base_url: Option<String>,
+    pub trust_forwarded_for: bool,
+    pub country_header: Option<HeaderName>,
+    pub default_redirect_status: StatusCode,
+}
+
+#[cfg(test)]
+impl AppState {
+    // Never connects to a database, so only paths that stay off it can be exercised; "boot" is the
+    // bootstrap key.
+    pub(crate) fn for_tests() -> Self {
+        use std::sync::Arc;
+
+        use crate::{
+            codegen::SequentialCodeGenerator,
+            config::config::{CacheConfig, VisitsConfig},
+            services::{id_allocator::IdAllocator, short_url_cache::ShortUrlCache},
+        };
+
+        let db = sea_orm::DatabaseConnection::default();
+        let short_url_service = ShortUrlService::new(
+            db.clone(),
+            Arc::new(SequentialCodeGenerator),
+            IdAllocator::new(1),
+            ShortUrlCache::new(&CacheConfig::default()),
+        );
+        let visit_service = VisitService::new(db.clone());
+        let (visit_recorder, _) =
+            VisitRecorder::spawn(short_url_service.clone(), visit_service.clone(), &VisitsConfig::default());
+        Self {
+            short_url_service,
+            visit_service,
+            visit_recorder,
+            api_key_service: ApiKeyService::new(db.clone(), Some("boot")),
+            user_service: UserService::new(db),
+            jwt_service: None,
+            create_limiter: None,
+            redirect_limiter: None,
+            password_limiter: None,
+            base_url: None,
+            trust_forwarded_for: false,
+            country_header: None,
+            default_redirect_status: StatusCode::TEMPORARY_REDIRECT,
+        }
+    }
 }
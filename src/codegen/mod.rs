@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use crate::config::config::{ShortCodeConfig, ShortCodeStrategy};
+
+pub mod obfuscated;
+pub mod pronounceable;
+pub mod random;
+pub mod sequential;
+
+pub use obfuscated::ObfuscatedCodeGenerator;
+pub use pronounceable::PronounceableCodeGenerator;
+pub use random::RandomCodeGenerator;
+pub use sequential::SequentialCodeGenerator;
+
+pub trait CodeGenerator: Send + Sync {
+    fn generate(&self, id: i64) -> String;
+
+    // Every attempt draws a fresh id, so id-derived codes can step past a collision too.
+    fn max_attempts(&self) -> usize {
+        3
+    }
+
+    // Whether an alias could be handed out by this generator in the future.
+    fn reserves(&self, _alias: &str) -> bool {
+        false
+    }
+}
+
+pub fn from_config(config: &ShortCodeConfig) -> Arc<dyn CodeGenerator> {
+    match config.strategy {
+        ShortCodeStrategy::Sequential => Arc::new(SequentialCodeGenerator),
+        ShortCodeStrategy::Obfuscated => {
+            let secret = config
+                .secret
+                .as_deref()
+                .filter(|secret| !secret.is_empty())
+                .expect("short_code.secret is required for the obfuscated strategy");
+            Arc::new(ObfuscatedCodeGenerator::new(secret))
+        }
+        ShortCodeStrategy::Random => {
+            assert!(
+                (4..=32).contains(&config.length),
+                "short_code.length must be between 4 and 32"
+            );
+            Arc::new(RandomCodeGenerator::new(config.length))
+        }
+        ShortCodeStrategy::Pronounceable => {
+            assert!(
+                (2..=4).contains(&config.words),
+                "short_code.words must be between 2 and 4"
+            );
+            Arc::new(PronounceableCodeGenerator::new(config.words))
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    codegen::CodeGenerator,
+    utils::{base62, permutation},
+};
+
+pub struct ObfuscatedCodeGenerator {
+    key: u64,
+}
+
+impl ObfuscatedCodeGenerator {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: permutation::derive_key(secret),
+        }
+    }
+}
+
+impl CodeGenerator for ObfuscatedCodeGenerator {
+    // The leading zero digit keeps these apart from canonical sequential codes.
+    fn generate(&self, id: i64) -> String {
+        format!(
+            "{}{}",
+            base62::ZERO_DIGIT,
+            base62::encode(permutation::permute(id, self.key))
+        )
+    }
+
+    fn reserves(&self, alias: &str) -> bool {
+        alias
+            .strip_prefix(base62::ZERO_DIGIT)
+            .is_some_and(base62::is_canonical)
+    }
+}
@@ -0,0 +1,57 @@
+use rand::seq::SliceRandom;
+
+use crate::codegen::CodeGenerator;
+
+const MAX_ATTEMPTS: usize = 8;
+const SEPARATOR: &str = "-";
+
+const WORDS: &[&str] = &[
+    "acorn", "amber", "anchor", "apple", "arrow", "aspen", "atlas", "badge", "bagel", "baker",
+    "bamboo", "banjo", "barley", "basil", "beacon", "bean", "birch", "bison", "blaze", "bloom",
+    "bluff", "bolt", "bonus", "breeze", "brick", "brook", "buddy", "bugle", "cabin", "cactus",
+    "camel", "candle", "canoe", "canyon", "caramel", "cargo", "carrot", "cedar", "cello", "chalk",
+    "charm", "cherry", "chess", "cider", "cinder", "citrus", "clay", "clover", "cobalt", "cocoa",
+    "comet", "coral", "cotton", "cove", "crane", "crater", "cricket", "crisp", "crown", "cube",
+    "cumin", "daisy", "dawn", "delta", "denim", "dingo", "dolphin", "dove", "drift", "drum",
+    "dune", "eagle", "echo", "ember", "emerald", "fable", "falcon", "fern", "fiddle", "fig",
+    "finch", "fjord", "flame", "flint", "flute", "foam", "forest", "fossil", "fox", "frost",
+    "gale", "garnet", "gecko", "ginger", "glacier", "globe", "gopher", "granite", "grape",
+    "gravel", "grove", "gull", "harbor", "hazel", "heron", "hickory", "honey", "hopper", "husky",
+    "igloo", "indigo", "iris", "ivory", "jade", "jasmine", "jelly", "jetty", "juniper", "kayak",
+    "kelp", "kettle", "kiwi", "koala", "lagoon", "lantern", "larch", "lava", "lemon", "lilac",
+    "lily", "linen", "llama", "lotus", "lunar", "lynx", "magnet", "mango", "maple", "marble",
+    "meadow", "melon", "mesa", "mint", "mocha", "moose", "mosaic", "moss", "nectar", "nimbus",
+    "noodle", "nutmeg", "oak", "oasis", "ocean", "olive", "onyx", "opal", "orbit", "orchid",
+    "otter", "owl", "paddle", "panda", "papaya", "parsley", "pebble", "pecan", "pepper", "piano",
+    "pine", "pixel", "plum", "polar", "pony", "poppy", "prairie", "prism", "puffin", "pumpkin",
+    "quail", "quartz", "quill", "rabbit", "radish", "rain", "raven", "reef", "ridge", "river",
+    "robin", "rocket", "rose", "ruby", "saffron", "sage", "salmon", "sandal", "satin", "scout",
+    "shell", "sierra", "silver", "sky", "slate", "sonnet", "sparrow", "spruce", "squid", "star",
+    "stone", "storm", "sugar", "summit", "sunny", "swan", "tango", "teal", "thistle", "thunder",
+    "tiger", "timber", "toffee", "topaz", "tulip", "tundra", "turtle", "umber", "valley", "velvet",
+    "violet", "walnut", "willow", "winter", "wren", "yarrow", "zebra", "zephyr", "zinc",
+];
+
+pub struct PronounceableCodeGenerator {
+    words: usize,
+}
+
+impl PronounceableCodeGenerator {
+    pub fn new(words: usize) -> Self {
+        Self { words }
+    }
+}
+
+impl CodeGenerator for PronounceableCodeGenerator {
+    fn generate(&self, _id: i64) -> String {
+        let mut rng = rand::thread_rng();
+        (0..self.words)
+            .map(|_| *WORDS.choose(&mut rng).expect("word list to be non-empty"))
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+    }
+
+    fn max_attempts(&self) -> usize {
+        MAX_ATTEMPTS
+    }
+}
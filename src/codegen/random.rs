@@ -0,0 +1,23 @@
+use crate::{codegen::CodeGenerator, utils::base62};
+
+const MAX_ATTEMPTS: usize = 5;
+
+pub struct RandomCodeGenerator {
+    length: usize,
+}
+
+impl RandomCodeGenerator {
+    pub fn new(length: usize) -> Self {
+        Self { length }
+    }
+}
+
+impl CodeGenerator for RandomCodeGenerator {
+    fn generate(&self, _id: i64) -> String {
+        base62::random(self.length)
+    }
+
+    fn max_attempts(&self) -> usize {
+        MAX_ATTEMPTS
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{codegen::CodeGenerator, utils::base62};
+
+// Codes up to this length cover ids below 62^5 (about 916 million). Longer aliases stay available;
+// should the sequence ever reach them, the insert collides and the next id is used instead.
+const RESERVED_LEN: usize = 5;
+
+pub struct SequentialCodeGenerator;
+
+impl CodeGenerator for SequentialCodeGenerator {
+    fn generate(&self, id: i64) -> String {
+        base62::encode(id)
+    }
+
+    fn reserves(&self, alias: &str) -> bool {
+        alias.len() <= RESERVED_LEN && base62::is_canonical(alias)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_short_generated_codes() {
+        let generator = SequentialCodeGenerator;
+        assert!(generator.reserves(&generator.generate(1)));
+        assert!(generator.reserves(&generator.generate(916_132_831)));
+    }
+
+    #[test]
+    fn accepts_word_aliases() {
+        let generator = SequentialCodeGenerator;
+        for alias in ["summer", "launch", "springsale", "promo2024"] {
+            assert!(!generator.reserves(alias), "{alias} should be available");
+        }
+    }
+}
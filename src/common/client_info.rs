@@ -0,0 +1,152 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap, HeaderName},
+};
+
+use url::Url;
+
+use crate::app::AppState;
+
+const MAX_HEADER_LEN: usize = 1024;
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub country: Option<String>,
+}
+
+impl ClientInfo {
+    // Drops the host part of the address: the last octet for IPv4, everything past /48 for IPv6.
+    pub fn anonymized_ip(&self) -> Option<String> {
+        self.ip.map(|ip| match ip {
+            IpAddr::V4(v4) => {
+                let [a, b, c, _] = v4.octets();
+                Ipv4Addr::new(a, b, c, 0).to_string()
+            }
+            IpAddr::V6(v6) => {
+                let [a, b, c, ..] = v6.segments();
+                Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0).to_string()
+            }
+        })
+    }
+
+    pub fn referrer_domain(&self) -> Option<String> {
+        let referrer = Url::parse(self.referrer.as_deref()?).ok()?;
+        let host = referrer.host_str()?.to_ascii_lowercase();
+        Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+    }
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let forwarded = if state.trust_forwarded_for {
+            forwarded_ip(&parts.headers)
+        } else {
+            None
+        };
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(Self {
+            ip: forwarded.or(peer),
+            referrer: header_value(&parts.headers, header::REFERER),
+            user_agent: header_value(&parts.headers, header::USER_AGENT),
+            accept_language: header_value(&parts.headers, header::ACCEPT_LANGUAGE),
+            country: state
+                .country_header
+                .as_ref()
+                .and_then(|name| country_code(&parts.headers, name.clone())),
+        })
+    }
+}
+
+// Clients can send their own X-Forwarded-For, so only the entry appended by our proxy is trusted.
+fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get(X_FORWARDED_FOR)?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn country_code(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    let value = header_value(headers, name)?;
+    if value.len() != 2 || !value.chars().all(|ch| ch.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(value.to_ascii_uppercase())
+}
+
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    let value = headers.get(name)?.to_str().ok()?.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.chars().take(MAX_HEADER_LEN).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    const PEER: &str = "192.0.2.10";
+
+    async fn client_ip(trust_forwarded_for: bool, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let mut request = Request::builder().uri("/abc");
+        if let Some(value) = forwarded_for {
+            request = request.header(X_FORWARDED_FOR, value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::new(PEER.parse().unwrap(), 40_000)));
+        let state = AppState {
+            trust_forwarded_for,
+            ..AppState::for_tests()
+        };
+        let Ok(client) = ClientInfo::from_request_parts(&mut parts, &state).await;
+        client.ip
+    }
+
+    #[tokio::test]
+    async fn ignores_forwarded_for_unless_trusted() {
+        assert_eq!(client_ip(false, Some("203.0.113.7")).await, PEER.parse().ok());
+        assert_eq!(client_ip(false, None).await, PEER.parse().ok());
+    }
+
+    #[tokio::test]
+    async fn takes_the_entry_appended_by_the_proxy() {
+        assert_eq!(client_ip(true, Some("203.0.113.7")).await, "203.0.113.7".parse().ok());
+        // The left-most entries come from the client and may be forged.
+        assert_eq!(
+            client_ip(true, Some("10.9.9.9, 198.51.100.1 , 203.0.113.7")).await,
+            "203.0.113.7".parse().ok()
+        );
+        assert_eq!(client_ip(true, Some("2001:db8::1")).await, "2001:db8::1".parse().ok());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_peer_for_unusable_headers() {
+        assert_eq!(client_ip(true, None).await, PEER.parse().ok());
+        assert_eq!(client_ip(true, Some("203.0.113.7, unknown")).await, PEER.parse().ok());
+    }
+}
@@ -0,0 +1,192 @@
+use url::Url;
+
+use crate::models::short_url::Model;
+
+// Where a redirect should go once the link itself has been resolved. `target` is `original_url` or
+// the URL of a matching targeting rule, `rest` the path after `/{code}/` and `query` the raw incoming
+// query string; the last two are only used when the link opts in.
+pub fn build(target: &str, model: &Model, rest: Option<&str>, query: Option<&str>) -> String {
+    let forward_path = model.forward_path && rest.is_some_and(|rest| !rest.is_empty() && is_forwardable_path(rest));
+    let forward_query = model.forward_query && query.is_some_and(|query| !query.is_empty());
+    let utm = utm_tags(model);
+    if !forward_path && !forward_query && utm.is_empty() {
+        return target.to_string();
+    }
+    // Destinations are validated on write, so this only fails for rows edited by hand.
+    let Ok(mut url) = Url::parse(target) else {
+        return target.to_string();
+    };
+
+    if forward_path && let Some(rest) = rest {
+        let path = format!("{}/{}", url.path().trim_end_matches('/'), rest.trim_start_matches('/'));
+        url.set_path(&path);
+    }
+    if forward_query && let Some(query) = query {
+        merge_query(&mut url, url::form_urlencoded::parse(query.as_bytes()).into_owned().collect());
+    }
+    // Tags fill in missing parameters unless the link says they override what is already there,
+    // including parameters forwarded from the visitor.
+    if model.utm_override {
+        merge_query(&mut url, utm);
+    } else {
+        let missing: Vec<(String, String)> = utm
+            .into_iter()
+            .filter(|(name, _)| !url.query_pairs().any(|(existing, _)| existing == name.as_str()))
+            .collect();
+        if !missing.is_empty() {
+            url.query_pairs_mut().extend_pairs(missing);
+        }
+    }
+    url.to_string()
+}
+
+// `rest` is already percent-decoded, and `Url::set_path` resolves dot segments (also encoded ones,
+// and `\` counts as a separator), so these could climb above the destination's own path.
+pub fn is_forwardable_path(rest: &str) -> bool {
+    !rest.split(['/', '\\']).any(|segment| {
+        matches!(
+            segment.to_ascii_lowercase().as_str(),
+            "." | ".." | "%2e" | "%2e%2e" | ".%2e" | "%2e."
+        )
+    })
+}
+
+fn utm_tags(model: &Model) -> Vec<(String, String)> {
+    [
+        ("utm_source", &model.utm_source),
+        ("utm_medium", &model.utm_medium),
+        ("utm_campaign", &model.utm_campaign),
+        ("utm_term", &model.utm_term),
+        ("utm_content", &model.utm_content),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.as_ref().map(|value| (name.to_string(), value.clone())))
+    .collect()
+}
+
+// Incoming parameters replace destination parameters with the same name; the rest keep their order.
+fn merge_query(url: &mut Url, incoming: Vec<(String, String)>) {
+    if incoming.is_empty() {
+        return;
+    }
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !incoming.iter().any(|(incoming_name, _)| incoming_name == name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(incoming);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn link() -> Model {
+        let now = Utc::now().fixed_offset();
+        Model {
+            id: 10_001,
+            short_code: "abc".to_string(),
+            original_url: "https://example.com/landing?ref=link".to_string(),
+            visit_count: 0,
+            status: 1,
+            is_deleted: 0,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            expires_at: None,
+            owner_id: None,
+            password_hash: None,
+            max_visits: None,
+            activates_at: None,
+            prelaunch_url: None,
+            redirect_status: None,
+            title: None,
+            forward_query: false,
+            forward_path: false,
+            utm_source: None,
+            utm_medium: None,
+            utm_campaign: None,
+            utm_term: None,
+            utm_content: None,
+            utm_override: false,
+            targeting_rules: None,
+            sticky_variants: false,
+            disabled_by_admin: false,
+        }
+    }
+
+    #[test]
+    fn ignores_path_and_query_unless_enabled() {
+        let model = link();
+        let location = build(&model.original_url, &model, Some("docs"), Some("ref=visitor"));
+        assert_eq!(location, "https://example.com/landing?ref=link");
+    }
+
+    #[test]
+    fn appends_forwarded_path() {
+        let model = Model {
+            forward_path: true,
+            ..link()
+        };
+        let location = build("https://example.com/landing/", &model, Some("/docs/intro"), None);
+        assert_eq!(location, "https://example.com/landing/docs/intro");
+    }
+
+    #[test]
+    fn never_forwards_dot_segments() {
+        let model = Model {
+            forward_path: true,
+            ..link()
+        };
+        for rest in ["../../admin", "docs/../../admin", "./..", "..\\admin", "%2e%2e/admin"] {
+            assert!(!is_forwardable_path(rest), "{rest}");
+            let location = build("https://example.com/landing/", &model, Some(rest), None);
+            assert_eq!(location, "https://example.com/landing/");
+        }
+        assert!(is_forwardable_path("docs/v1.2/..intro"));
+    }
+
+    #[test]
+    fn forwarded_query_replaces_matching_parameters() {
+        let model = Model {
+            forward_query: true,
+            ..link()
+        };
+        let location = build(&model.original_url, &model, None, Some("id=7&ref=visitor"));
+        assert_eq!(location, "https://example.com/landing?id=7&ref=visitor");
+    }
+
+    #[test]
+    fn utm_tags_fill_in_missing_parameters() {
+        let model = Model {
+            forward_query: true,
+            utm_source: Some("newsletter".to_string()),
+            utm_medium: Some("email".to_string()),
+            ..link()
+        };
+        let location = build(&model.original_url, &model, None, Some("utm_source=twitter"));
+        assert_eq!(
+            location,
+            "https://example.com/landing?ref=link&utm_source=twitter&utm_medium=email"
+        );
+    }
+
+    #[test]
+    fn utm_override_replaces_forwarded_parameters() {
+        let model = Model {
+            forward_query: true,
+            forward_path: true,
+            utm_source: Some("newsletter".to_string()),
+            utm_override: true,
+            ..link()
+        };
+        let location = build(&model.original_url, &model, Some("docs"), Some("utm_source=twitter&id=7"));
+        assert_eq!(location, "https://example.com/landing/docs?ref=link&id=7&utm_source=newsletter");
+    }
+}
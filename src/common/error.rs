@@ -32,9 +32,14 @@ impl ValidationErrors {
 #[derive(Debug)]
 pub enum AppError {
     InvalidInput { detail: String, errors: Option<ValidationErrors> },
+    Unauthorized { detail: String },
+    Forbidden { detail: String },
     NotFound { detail: String },
     Conflict { detail: String },
     Gone { detail: String },
+    NotYetActive { detail: String, activates_at: String, prelaunch_url: Option<String> },
+    TooManyRequests { detail: String, retry_after_secs: u64 },
+    ServiceUnavailable { detail: String },
     Internal { detail: String },
 }
 
@@ -53,6 +58,14 @@ impl AppError {
         }
     }
 
+    pub fn unauthorized(detail: impl Into<String>) -> Self {
+        Self::Unauthorized { detail: detail.into() }
+    }
+
+    pub fn forbidden(detail: impl Into<String>) -> Self {
+        Self::Forbidden { detail: detail.into() }
+    }
+
     pub fn not_found(detail: impl Into<String>) -> Self {
         Self::NotFound { detail: detail.into() }
     }
@@ -65,6 +78,29 @@ impl AppError {
         Self::Gone { detail: detail.into() }
     }
 
+    pub fn not_yet_active(
+        detail: impl Into<String>,
+        activates_at: impl Into<String>,
+        prelaunch_url: Option<String>,
+    ) -> Self {
+        Self::NotYetActive {
+            detail: detail.into(),
+            activates_at: activates_at.into(),
+            prelaunch_url,
+        }
+    }
+
+    pub fn too_many_requests(detail: impl Into<String>, retry_after_secs: u64) -> Self {
+        Self::TooManyRequests {
+            detail: detail.into(),
+            retry_after_secs,
+        }
+    }
+
+    pub fn service_unavailable(detail: impl Into<String>) -> Self {
+        Self::ServiceUnavailable { detail: detail.into() }
+    }
+
     pub fn internal(detail: impl Into<String>) -> Self {
         Self::Internal { detail: detail.into() }
     }
@@ -72,9 +108,14 @@ impl AppError {
     fn status(&self) -> StatusCode {
         match self {
             Self::InvalidInput { .. } => StatusCode::BAD_REQUEST,
+            Self::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            Self::Forbidden { .. } => StatusCode::FORBIDDEN,
             Self::NotFound { .. } => StatusCode::NOT_FOUND,
             Self::Conflict { .. } => StatusCode::CONFLICT,
             Self::Gone { .. } => StatusCode::GONE,
+            Self::NotYetActive { .. } => StatusCode::FORBIDDEN,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
             Self::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -82,19 +123,50 @@ impl AppError {
     fn title(&self) -> &'static str {
         match self {
             Self::InvalidInput { .. } => "Invalid input",
+            Self::Unauthorized { .. } => "Unauthorized",
+            Self::Forbidden { .. } => "Forbidden",
             Self::NotFound { .. } => "Not found",
             Self::Conflict { .. } => "Conflict",
             Self::Gone { .. } => "Gone",
+            Self::NotYetActive { .. } => "Not yet active",
+            Self::TooManyRequests { .. } => "Too many requests",
+            Self::ServiceUnavailable { .. } => "Service unavailable",
             Self::Internal { .. } => "Internal error",
         }
     }
 
+    fn extensions(&self) -> HashMap<String, serde_json::Value> {
+        let mut extensions = HashMap::new();
+        match self {
+            Self::NotYetActive {
+                activates_at,
+                prelaunch_url,
+                ..
+            } => {
+                extensions.insert("activatesAt".to_string(), serde_json::json!(activates_at));
+                if let Some(prelaunch_url) = prelaunch_url {
+                    extensions.insert("prelaunchUrl".to_string(), serde_json::json!(prelaunch_url));
+                }
+            }
+            Self::TooManyRequests { retry_after_secs, .. } => {
+                extensions.insert("retryAfter".to_string(), serde_json::json!(retry_after_secs));
+            }
+            _ => {}
+        }
+        extensions
+    }
+
     fn detail(&self) -> &str {
         match self {
             Self::InvalidInput { detail, .. }
+            | Self::Unauthorized { detail }
+            | Self::Forbidden { detail }
             | Self::NotFound { detail }
             | Self::Conflict { detail }
             | Self::Gone { detail }
+            | Self::NotYetActive { detail, .. }
+            | Self::TooManyRequests { detail, .. }
+            | Self::ServiceUnavailable { detail }
             | Self::Internal { detail } => detail,
         }
     }
@@ -112,6 +184,11 @@ impl IntoResponse for AppError {
             extensions: HashMap::new(),
         };
 
+        let retry_after_secs = match &self {
+            Self::TooManyRequests { retry_after_secs, .. } => Some(*retry_after_secs),
+            _ => None,
+        };
+        problem = problem.add_extension(self.extensions());
         if let Self::InvalidInput { errors: Some(errors), .. } = self {
             problem = problem.with_errors(errors.errors);
         }
@@ -128,11 +205,24 @@ impl IntoResponse for AppError {
             serde_json::to_string(&fallback).unwrap()
         });
 
-        (
+        let mut response = (
             status,
             [(axum::http::header::CONTENT_TYPE, "application/problem+json")],
             body,
         )
-            .into_response()
+            .into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                axum::http::header::WWW_AUTHENTICATE,
+                axum::http::HeaderValue::from_static("Bearer"),
+            );
+        }
+        if let Some(retry_after_secs) = retry_after_secs {
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                axum::http::HeaderValue::from(retry_after_secs),
+            );
+        }
+        response
     }
 }
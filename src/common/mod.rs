@@ -1,2 +1,11 @@
+pub mod client_info;
+pub mod destination;
 pub mod error;
+pub mod nullable;
+pub mod password;
+pub mod redirect;
+pub mod scope;
+pub mod targeting;
+pub mod user_agent;
 pub mod validation;
+pub mod variant;
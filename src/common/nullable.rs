@@ -0,0 +1,11 @@
+use serde::{Deserialize, Deserializer};
+
+// For `#[serde(default, deserialize_with = ...)]` on PATCH fields: an absent field stays `None`,
+// an explicit `null` becomes `Some(None)`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
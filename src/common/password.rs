@@ -0,0 +1,80 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use crate::{common::error::AppError, services::rate_limiter::RateLimiter};
+
+// Argon2 is deliberately slow, so both operations run off the async workers.
+pub async fn hash(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| AppError::internal(format!("failed to hash password: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::internal(format!("failed to hash password: {err}")))?
+}
+
+pub async fn verify(hash: String, password: String) -> bool {
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&hash)
+            .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+            .unwrap_or(false)
+    })
+    .await
+    .unwrap_or(false)
+}
+
+// Only wrong passwords are charged, and `key` names the link and the client, so visitors who know the
+// password never wait and guessing from one address does not lock anybody else out.
+pub async fn verify_throttled(
+    limiter: Option<&RateLimiter>,
+    key: &str,
+    hash: String,
+    password: String,
+) -> Result<bool, AppError> {
+    if let Some(limiter) = limiter
+        && let Err(retry_after_secs) = limiter.check(key)
+    {
+        return Err(AppError::too_many_requests("too many password attempts", retry_after_secs));
+    }
+    if verify(hash, password).await {
+        return Ok(true);
+    }
+    if let Some(limiter) = limiter {
+        let _ = limiter.acquire(key);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config::BucketConfig;
+
+    async fn attempt(limiter: &RateLimiter, hash: &str, ip: &str, password: &str) -> Result<bool, AppError> {
+        let key = format!("link:1:{}", ip);
+        verify_throttled(Some(limiter), &key, hash.to_string(), password.to_string()).await
+    }
+
+    #[tokio::test]
+    async fn correct_passwords_are_never_throttled() {
+        let limiter = RateLimiter::new(&BucketConfig { burst: 2, per_minute: 1 }, 100).unwrap();
+        let hash = hash("s3cret".to_string()).await.unwrap();
+        for _ in 0..3 {
+            assert!(matches!(attempt(&limiter, &hash, "10.0.0.1", "s3cret").await, Ok(true)));
+        }
+        // Someone guessing from another address does not lock this visitor out.
+        for _ in 0..2 {
+            assert!(matches!(attempt(&limiter, &hash, "10.0.0.2", "guess").await, Ok(false)));
+        }
+        assert!(matches!(
+            attempt(&limiter, &hash, "10.0.0.2", "guess").await,
+            Err(AppError::TooManyRequests { .. })
+        ));
+        assert!(matches!(attempt(&limiter, &hash, "10.0.0.1", "s3cret").await, Ok(true)));
+    }
+}
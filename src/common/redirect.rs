@@ -0,0 +1,30 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::common::error::{AppError, ValidationErrors};
+
+pub const REDIRECT_STATUSES: [u16; 4] = [301, 302, 307, 308];
+
+pub fn validate_redirect_status(input: u16, field_name: &str) -> Result<(), AppError> {
+    if REDIRECT_STATUSES.contains(&input) {
+        return Ok(());
+    }
+    Err(AppError::bad_request_with_errors(
+        format!("{} is invalid", field_name),
+        ValidationErrors::single(
+            field_name,
+            "INVALID_VALUE",
+            format!("{} must be 301, 302, 307 or 308", field_name),
+        ),
+    ))
+}
+
+// `axum::response::Redirect` only covers 303, 307 and 308.
+pub fn redirect(status: StatusCode, location: &str) -> Response {
+    match HeaderValue::try_from(location) {
+        Ok(location) => (status, [(header::LOCATION, location)]).into_response(),
+        Err(_) => AppError::internal("redirect location is not a valid header value").into_response(),
+    }
+}
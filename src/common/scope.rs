@@ -0,0 +1,56 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    LinksCreate,
+    LinksRead,
+    LinksWrite,
+    StatsRead,
+    Admin,
+}
+
+impl Scope {
+    // What a key may do when it was issued without explicit scopes.
+    pub const USER_DEFAULT: [Scope; 4] = [
+        Scope::LinksCreate,
+        Scope::LinksRead,
+        Scope::LinksWrite,
+        Scope::StatsRead,
+    ];
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "links:create" => Some(Self::LinksCreate),
+            "links:read" => Some(Self::LinksRead),
+            "links:write" => Some(Self::LinksWrite),
+            "stats:read" => Some(Self::StatsRead),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LinksCreate => "links:create",
+            Self::LinksRead => "links:read",
+            Self::LinksWrite => "links:write",
+            Self::StatsRead => "stats:read",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Stored as a space-separated list, the same form OAuth uses for `scope`.
+pub fn join(scopes: &[Scope]) -> String {
+    scopes.iter().map(Scope::as_str).collect::<Vec<_>>().join(" ")
+}
+
+pub fn split(input: &str) -> Vec<Scope> {
+    input.split_whitespace().filter_map(Scope::parse).collect()
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    common::{client_info::ClientInfo, user_agent},
+    models::short_url::Model,
+};
+
+pub const DEVICES: [&str; 4] = ["desktop", "mobile", "bot", "other"];
+
+// A rule matches when every condition it sets matches; each condition matches any of its values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetingRule {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<String>,
+}
+
+impl TargetingRule {
+    pub fn has_conditions(&self) -> bool {
+        !self.os.is_empty() || !self.devices.is_empty() || !self.languages.is_empty() || !self.countries.is_empty()
+    }
+
+    fn matches(&self, agent: &user_agent::UserAgent, languages: &[String], country: Option<&str>) -> bool {
+        let any = |values: &[String], actual: Option<&str>| {
+            values.is_empty()
+                || actual.is_some_and(|actual| values.iter().any(|value| value.eq_ignore_ascii_case(actual)))
+        };
+        any(&self.os, agent.os.as_deref())
+            && any(&self.devices, agent.device.as_deref())
+            && any(&self.countries, country)
+            && (self.languages.is_empty()
+                || self
+                    .languages
+                    .iter()
+                    .any(|wanted| languages.iter().any(|accepted| language_matches(wanted, accepted))))
+    }
+}
+
+pub fn rules(model: &Model) -> Vec<TargetingRule> {
+    model
+        .targeting_rules
+        .clone()
+        .and_then(|rules| serde_json::from_value(rules).ok())
+        .unwrap_or_default()
+}
+
+// Stored as `NULL` rather than an empty array so links without rules skip the lookup entirely.
+pub fn to_json(rules: &[TargetingRule]) -> Option<Value> {
+    if rules.is_empty() {
+        return None;
+    }
+    serde_json::to_value(rules).ok()
+}
+
+// The destination of the first matching rule, if any; the caller falls back to `original_url`.
+pub fn resolve(model: &Model, client: &ClientInfo) -> Option<String> {
+    model.targeting_rules.as_ref()?;
+    let agent = client
+        .user_agent
+        .as_deref()
+        .map(user_agent::parse)
+        .unwrap_or_default();
+    let languages = accepted_languages(client.accept_language.as_deref());
+    rules(model)
+        .into_iter()
+        .find(|rule| rule.matches(&agent, &languages, client.country.as_deref()))
+        .map(|rule| rule.url)
+}
+
+// Language ranges from `Accept-Language`, skipping `*` and anything the client marked with `q=0`.
+fn accepted_languages(header: Option<&str>) -> Vec<String> {
+    let Some(header) = header else {
+        return Vec::new();
+    };
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let rejected = parts.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .is_some_and(|q| q <= 0.0)
+            });
+            (!tag.is_empty() && tag != "*" && !rejected).then(|| tag.to_string())
+        })
+        .collect()
+}
+
+// `de` matches `de` and `de-AT`; `de-AT` only matches itself.
+fn language_matches(wanted: &str, accepted: &str) -> bool {
+    accepted.eq_ignore_ascii_case(wanted)
+        || (accepted
+            .get(..wanted.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(wanted))
+            && accepted[wanted.len()..].starts_with('-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_prefix_matches_regional_variants() {
+        assert!(language_matches("de", "de"));
+        assert!(language_matches("de", "de-AT"));
+        assert!(language_matches("DE", "de-at"));
+        assert!(language_matches("de-AT", "de-at"));
+        assert!(!language_matches("de-AT", "de"));
+        assert!(!language_matches("de-AT", "de-CH"));
+        assert!(!language_matches("de", "dear"));
+        assert!(!language_matches("zh-Hant", "zh"));
+    }
+
+    #[test]
+    fn accepted_languages_skip_wildcards_and_rejected_ranges() {
+        let accepted = accepted_languages(Some("fr-CH, fr;q=0.9, en;q=0, *;q=0.5, de ; q=0.1"));
+        assert_eq!(accepted, ["fr-CH", "fr", "de"]);
+        assert!(accepted_languages(None).is_empty());
+    }
+}
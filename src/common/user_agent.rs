@@ -0,0 +1,54 @@
+use std::sync::OnceLock;
+
+use woothee::parser::Parser;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserAgent {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: Option<String>,
+}
+
+fn parser() -> &'static Parser {
+    static PARSER: OnceLock<Parser> = OnceLock::new();
+    PARSER.get_or_init(Parser::new)
+}
+
+pub fn parse(input: &str) -> UserAgent {
+    let Some(result) = parser().parse(input) else {
+        return UserAgent::default();
+    };
+
+    UserAgent {
+        browser: known(result.name).map(str::to_string),
+        os: known(result.os).map(normalize_os),
+        device: normalize_device(result.category).map(str::to_string),
+    }
+}
+
+fn known(value: &str) -> Option<&str> {
+    match value {
+        "" | "UNKNOWN" => None,
+        value => Some(value),
+    }
+}
+
+// Tablets are reported as smartphones by woothee, so they count as mobile too.
+fn normalize_device(category: &str) -> Option<&'static str> {
+    match category {
+        "pc" => Some("desktop"),
+        "smartphone" | "mobilephone" => Some("mobile"),
+        "crawler" => Some("bot"),
+        "appliance" => Some("other"),
+        _ => None,
+    }
+}
+
+fn normalize_os(os: &str) -> String {
+    match os {
+        "iPhone" | "iPad" | "iPod" | "iOS" => "iOS".to_string(),
+        "Mac OSX" | "Mac OS Classic" => "macOS".to_string(),
+        os if os.starts_with("Windows") && os != "Windows Phone OS" => "Windows".to_string(),
+        os => os.to_string(),
+    }
+}
@@ -1,10 +1,16 @@
+use sea_orm::prelude::DateTimeWithTimeZone;
 use url::Url;
 
-use crate::common::error::{AppError, ValidationErrors};
+use crate::common::{
+    error::{AppError, ValidationErrors},
+    targeting::{TargetingRule, DEVICES},
+};
 
 const CODE_REQUIRED: &str = "REQUIRED";
 const CODE_INVALID_FORMAT: &str = "INVALID_FORMAT";
 const CODE_INVALID_SCHEME: &str = "INVALID_SCHEME";
+const CODE_INVALID_LENGTH: &str = "INVALID_LENGTH";
+const CODE_RESERVED: &str = "RESERVED";
 
 pub fn validate_url(input: &str, field_name: &str) -> Result<(), AppError> {
     if input.trim().is_empty() {
@@ -41,3 +47,225 @@ pub fn validate_url(input: &str, field_name: &str) -> Result<(), AppError> {
         )),
     }
 }
+
+const ALIAS_MIN_LEN: usize = 3;
+const ALIAS_MAX_LEN: usize = 32;
+const RESERVED_ALIASES: &[&str] = &["api", "admin"];
+
+pub fn validate_alias(input: &str, field_name: &str) -> Result<(), AppError> {
+    let len = input.chars().count();
+    if !(ALIAS_MIN_LEN..=ALIAS_MAX_LEN).contains(&len) {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} length is invalid", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_LENGTH,
+                format!(
+                    "{} must be between {} and {} characters",
+                    field_name, ALIAS_MIN_LEN, ALIAS_MAX_LEN
+                ),
+            ),
+        ));
+    }
+
+    if !input
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} is invalid", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_FORMAT,
+                format!("{} may only contain letters, digits, '-' and '_'", field_name),
+            ),
+        ));
+    }
+
+    if RESERVED_ALIASES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(input))
+    {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} is reserved", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_RESERVED,
+                format!("{} is reserved", field_name),
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+const PASSWORD_MIN_LEN: usize = 4;
+const PASSWORD_MAX_LEN: usize = 128;
+
+pub fn validate_password(input: &str, field_name: &str) -> Result<(), AppError> {
+    let len = input.chars().count();
+    if !(PASSWORD_MIN_LEN..=PASSWORD_MAX_LEN).contains(&len) {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} length is invalid", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_LENGTH,
+                format!(
+                    "{} must be between {} and {} characters",
+                    field_name, PASSWORD_MIN_LEN, PASSWORD_MAX_LEN
+                ),
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+const CODE_INVALID_RANGE: &str = "INVALID_RANGE";
+
+pub fn validate_max_visits(input: i64, field_name: &str) -> Result<(), AppError> {
+    if input < 1 {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} is invalid", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_RANGE,
+                format!("{} must be at least 1", field_name),
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_activation_window(
+    activates_at: Option<DateTimeWithTimeZone>,
+    expires_at: Option<DateTimeWithTimeZone>,
+) -> Result<(), AppError> {
+    if let (Some(activates_at), Some(expires_at)) = (activates_at, expires_at)
+        && activates_at >= expires_at
+    {
+        return Err(AppError::bad_request_with_errors(
+            "activatesAt must be before expiresAt",
+            ValidationErrors::single(
+                "activatesAt",
+                CODE_INVALID_RANGE,
+                "activatesAt must be before expiresAt",
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+const TITLE_MAX_LEN: usize = 200;
+const UTM_VALUE_MAX_LEN: usize = 200;
+
+// Trims the title; an empty title is treated as none.
+pub fn normalize_title(input: &str, field_name: &str) -> Result<Option<String>, AppError> {
+    normalize_text(input, field_name, TITLE_MAX_LEN)
+}
+
+pub fn normalize_utm_value(input: &str, field_name: &str) -> Result<Option<String>, AppError> {
+    normalize_text(input, field_name, UTM_VALUE_MAX_LEN)
+}
+
+fn normalize_text(input: &str, field_name: &str, max_len: usize) -> Result<Option<String>, AppError> {
+    let trimmed = input.trim();
+    if trimmed.chars().count() > max_len {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} length is invalid", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_LENGTH,
+                format!("{} must be at most {} characters", field_name, max_len),
+            ),
+        ));
+    }
+
+    Ok(Some(trimmed.to_string()).filter(|value| !value.is_empty()))
+}
+
+const CODE_INVALID_VALUE: &str = "INVALID_VALUE";
+const MAX_TARGETING_RULES: usize = 20;
+const TARGETING_VALUE_MAX_LEN: usize = 50;
+
+// Trims every value and upper-cases countries so matching at redirect time stays cheap.
+pub fn normalize_targeting_rules(
+    rules: Vec<TargetingRule>,
+    field_name: &str,
+) -> Result<Vec<TargetingRule>, AppError> {
+    if rules.len() > MAX_TARGETING_RULES {
+        return Err(AppError::bad_request_with_errors(
+            format!("{} has too many rules", field_name),
+            ValidationErrors::single(
+                field_name,
+                CODE_INVALID_LENGTH,
+                format!("{} must have at most {} rules", field_name, MAX_TARGETING_RULES),
+            ),
+        ));
+    }
+
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let field = format!("{}[{}]", field_name, index);
+            validate_url(&rule.url, &format!("{}.url", field))?;
+            let rule = TargetingRule {
+                url: rule.url,
+                os: targeting_values(rule.os, &format!("{}.os", field), |_| true)?,
+                devices: targeting_values(rule.devices, &format!("{}.devices", field), |device| {
+                    DEVICES.contains(&device.to_ascii_lowercase().as_str())
+                })?
+                .into_iter()
+                .map(|device| device.to_ascii_lowercase())
+                .collect(),
+                languages: targeting_values(rule.languages, &format!("{}.languages", field), |language| {
+                    language.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+                })?,
+                countries: targeting_values(rule.countries, &format!("{}.countries", field), |country| {
+                    country.len() == 2 && country.chars().all(|ch| ch.is_ascii_alphabetic())
+                })?
+                .into_iter()
+                .map(|country| country.to_ascii_uppercase())
+                .collect(),
+            };
+            if !rule.has_conditions() {
+                return Err(AppError::bad_request_with_errors(
+                    format!("{} has no conditions", field),
+                    ValidationErrors::single(
+                        &field,
+                        CODE_REQUIRED,
+                        format!("{} must set os, devices, languages or countries", field),
+                    ),
+                ));
+            }
+            Ok(rule)
+        })
+        .collect()
+}
+
+fn targeting_values(
+    values: Vec<String>,
+    field_name: &str,
+    is_valid: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, AppError> {
+    values
+        .into_iter()
+        .map(|value| {
+            let value = value.trim();
+            if value.is_empty() || value.len() > TARGETING_VALUE_MAX_LEN || !is_valid(value) {
+                return Err(AppError::bad_request_with_errors(
+                    format!("{} is invalid", field_name),
+                    ValidationErrors::single(
+                        field_name,
+                        CODE_INVALID_VALUE,
+                        format!("{} contains an invalid value: {}", field_name, value),
+                    ),
+                ));
+            }
+            Ok(value.to_string())
+        })
+        .collect()
+}
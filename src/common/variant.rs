@@ -0,0 +1,111 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use rand::Rng;
+
+use crate::models::short_url_variant::Model;
+
+const COOKIE_NAME: &str = "corto_variant";
+const COOKIE_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+// Weighted random pick. A sticky visitor keeps their variant while it exists and still gets traffic.
+pub fn pick(variants: &[Model], sticky: Option<i64>) -> Option<&Model> {
+    if let Some(id) = sticky
+        && let Some(variant) = variants.iter().find(|variant| variant.id == id && variant.weight > 0)
+    {
+        return Some(variant);
+    }
+
+    let total: i64 = variants.iter().map(|variant| variant.weight.max(0) as i64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rand::thread_rng().gen_range(0..total);
+    variants.iter().find(|variant| {
+        let weight = variant.weight.max(0) as i64;
+        if roll < weight {
+            return true;
+        }
+        roll -= weight;
+        false
+    })
+}
+
+pub fn sticky_variant(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == COOKIE_NAME).then(|| value.parse().ok()).flatten()
+        })
+}
+
+// Scoped to the link's path so every code keeps its own assignment.
+pub fn sticky_cookie(code: &str, variant_id: i64) -> Option<HeaderValue> {
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/{}; Max-Age={}; HttpOnly; SameSite=Lax",
+        COOKIE_NAME, variant_id, code, COOKIE_MAX_AGE_SECS
+    ))
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use super::*;
+
+    fn variants(weights: &[i32]) -> Vec<Model> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| Model {
+                id: index as i64 + 1,
+                short_url_id: 10_001,
+                url: format!("https://example.com/{}", index + 1),
+                weight: *weight,
+                created_at: Utc::now().fixed_offset(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picks_in_proportion_to_weight() {
+        let variants = variants(&[1, 3, 0]);
+        let mut counts: HashMap<i64, u32> = HashMap::new();
+        for _ in 0..20_000 {
+            *counts.entry(pick(&variants, None).unwrap().id).or_default() += 1;
+        }
+        assert!(!counts.contains_key(&3));
+        let share = f64::from(counts[&2]) / 20_000.0;
+        assert!((0.72..0.78).contains(&share), "variant 2 got {share}");
+    }
+
+    #[test]
+    fn nothing_to_pick_without_weight() {
+        assert!(pick(&variants(&[0, 0]), None).is_none());
+        assert!(pick(&[], None).is_none());
+    }
+
+    #[test]
+    fn sticky_visitors_keep_their_variant() {
+        let variants = variants(&[100, 1, 0]);
+        assert!((0..100).all(|_| pick(&variants, Some(2)).unwrap().id == 2));
+        // Gone or paused variants fall back to a weighted pick.
+        assert_ne!(pick(&variants, Some(3)).unwrap().id, 3);
+        assert!(pick(&variants, Some(42)).is_some());
+    }
+
+    #[test]
+    fn sticky_cookie_round_trips() {
+        let cookie = sticky_cookie("abc", 7).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark"));
+        let value = cookie.to_str().unwrap().split(';').next().unwrap().to_string();
+        headers.append(header::COOKIE, HeaderValue::from_str(&value).unwrap());
+        assert_eq!(sticky_variant(&headers), Some(7));
+    }
+}
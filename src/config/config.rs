@@ -1,3 +1,4 @@
+use jsonwebtoken::Algorithm;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -6,6 +7,22 @@ pub struct AppConfig {
     pub server: ServerConfig,
     pub datasource: DatasourceConfig,
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub short_code: ShortCodeConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub visits: VisitsConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub redirect: RedirectConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -18,6 +35,8 @@ pub struct ServerConfig {
     pub host: Option<String>,
     pub port: u16,
     pub base_url: Option<String>,
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -30,6 +49,221 @@ pub struct LoggingConfig {
     pub level: String,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShortCodeConfig {
+    #[serde(default)]
+    pub strategy: ShortCodeStrategy,
+    pub secret: Option<String>,
+    #[serde(default = "default_short_code_length")]
+    pub length: usize,
+    #[serde(default = "default_short_code_words")]
+    pub words: usize,
+    #[serde(default = "default_id_block_size")]
+    pub id_block_size: i64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortCodeStrategy {
+    #[default]
+    Sequential,
+    Obfuscated,
+    Random,
+    Pronounceable,
+}
+
+impl Default for ShortCodeConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ShortCodeStrategy::default(),
+            secret: None,
+            length: default_short_code_length(),
+            words: default_short_code_words(),
+            id_block_size: default_id_block_size(),
+        }
+    }
+}
+
+fn default_short_code_length() -> usize {
+    7
+}
+
+fn default_short_code_words() -> usize {
+    3
+}
+
+fn default_id_block_size() -> i64 {
+    32
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AnalyticsConfig {
+    pub country_header: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct VisitsConfig {
+    pub channel_capacity: usize,
+    pub batch_size: usize,
+    pub flush_interval_ms: u64,
+}
+
+impl Default for VisitsConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 10_000,
+            batch_size: 500,
+            flush_interval_ms: 1_000,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub max_capacity: u64,
+    pub ttl_secs: u64,
+    pub negative_ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_capacity: 10_000,
+            ttl_secs: 60,
+            negative_ttl_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthConfig {
+    pub bootstrap_api_key: Option<String>,
+    pub jwt: Option<JwtConfig>,
+}
+
+// Bearer JWTs are accepted only when this section is present.
+#[derive(Debug, Deserialize, Clone)]
+pub struct JwtConfig {
+    pub jwks_path: Option<String>,
+    pub jwks_url: Option<String>,
+    pub issuer: String,
+    pub audience: Option<String>,
+    #[serde(default = "default_jwt_leeway_secs")]
+    pub leeway_secs: u64,
+    #[serde(default = "default_jwks_refresh_secs")]
+    pub jwks_refresh_secs: u64,
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    #[serde(default = "default_roles_claim")]
+    pub roles_claim: String,
+    #[serde(default = "default_admin_roles")]
+    pub admin_roles: Vec<String>,
+    #[serde(default = "default_jwt_algorithms")]
+    pub algorithms: Vec<Algorithm>,
+}
+
+fn default_jwt_leeway_secs() -> u64 {
+    60
+}
+
+fn default_jwks_refresh_secs() -> u64 {
+    300
+}
+
+fn default_username_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_roles_claim() -> String {
+    "roles".to_string()
+}
+
+fn default_admin_roles() -> Vec<String> {
+    vec!["admin".to_string()]
+}
+
+// Asymmetric algorithms only: an HMAC secret has no place in a public JWKS.
+pub(crate) fn default_jwt_algorithms() -> Vec<Algorithm> {
+    vec![
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+        Algorithm::PS384,
+        Algorithm::PS512,
+        Algorithm::ES256,
+        Algorithm::ES384,
+        Algorithm::EdDSA,
+    ]
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    // Upper bound on tracked clients per budget; idle clients are forgotten once their bucket is full again.
+    pub max_clients: u64,
+    pub create: BucketConfig,
+    pub redirect: BucketConfig,
+    // Failed attempts per link and client IP. Not affected by `enabled`; `per_minute = 0` turns it off.
+    pub password: BucketConfig,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_clients: 100_000,
+            create: BucketConfig {
+                burst: 20,
+                per_minute: 60,
+            },
+            redirect: BucketConfig {
+                burst: 200,
+                per_minute: 1_200,
+            },
+            password: BucketConfig {
+                burst: 5,
+                per_minute: 5,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RedirectConfig {
+    // Used by links without their own `redirectStatus`.
+    pub default_status: u16,
+}
+
+impl Default for RedirectConfig {
+    fn default() -> Self {
+        Self { default_status: 307 }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    // How often due destination changes are applied; scheduled switches may lag by up to this much.
+    pub interval_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self { interval_secs: 15 }
+    }
+}
+
 pub fn load_configuration() -> Result<AppConfig, config::ConfigError> {
     let builder = config::Config::builder()
         .add_source(config::File::with_name("config").required(true))
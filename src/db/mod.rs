@@ -2,6 +2,7 @@ use sea_orm::{Database, DatabaseConnection};
 
 use crate::config::config::DatasourceConfig;
 
+#[allow(clippy::let_and_return)]
 pub async fn init_db(datasource: &DatasourceConfig) -> DatabaseConnection {
     let db = Database::connect(&datasource.url)
         .await
@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    common::error::{AppError, ValidationErrors},
+    common::scope::{self, Scope},
+    handlers::short_url_handler::parse_timestamp,
+    middleware::auth::{scopes, Authorized},
+    models::api_key::Model,
+    services::{api_key_service::NewApiKey, user_service::UserRole},
+};
+
+const NAME_MAX_LEN: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub user_id: Option<i64>,
+    pub scopes: Option<Vec<String>>,
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    pub key: String,
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > NAME_MAX_LEN {
+        return Err(AppError::bad_request_with_errors(
+            "name is invalid",
+            ValidationErrors::single(
+                "name",
+                "INVALID_LENGTH",
+                format!("name must be between 1 and {} characters", NAME_MAX_LEN),
+            ),
+        ));
+    }
+    let scopes = payload.scopes.map(parse_scopes).transpose()?;
+    let expires_at = parse_timestamp(payload.expires_at, "expiresAt")?;
+    if let Some(expires_at) = expires_at
+        && expires_at <= chrono::Utc::now().fixed_offset()
+    {
+        return Err(AppError::bad_request_with_errors(
+            "expires_at is invalid",
+            ValidationErrors::single("expiresAt", "INVALID_RANGE", "expires_at must be in the future"),
+        ));
+    }
+
+    if let Some(user_id) = payload.user_id {
+        let user = state.user_service.find_by_id(user_id).await?;
+        let grants_admin = scopes
+            .as_ref()
+            .is_some_and(|scopes| scopes.contains(&Scope::Admin));
+        if grants_admin && UserRole::parse(&user.role) != Some(UserRole::Admin) {
+            return Err(AppError::bad_request_with_errors(
+                "scopes are invalid",
+                ValidationErrors::single("scopes", "NOT_ALLOWED", "only admin users can hold the admin scope"),
+            ));
+        }
+    }
+
+    let (model, key) = state
+        .api_key_service
+        .create_key(NewApiKey {
+            name,
+            user_id: payload.user_id,
+            scopes,
+            expires_at,
+        })
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            api_key: to_response(model),
+            key,
+        }),
+    ))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+) -> Result<impl IntoResponse, AppError> {
+    let keys = state.api_key_service.list_keys().await?;
+    Ok(Json(keys.into_iter().map(to_response).collect::<Vec<_>>()))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    state.api_key_service.revoke_key(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn parse_scopes(input: Vec<String>) -> Result<Vec<Scope>, AppError> {
+    let mut scopes = Vec::with_capacity(input.len());
+    for value in input {
+        let scope = Scope::parse(value.trim()).ok_or_else(|| {
+            AppError::bad_request_with_errors(
+                "scopes are invalid",
+                ValidationErrors::single(
+                    "scopes",
+                    "INVALID_VALUE",
+                    format!("unknown scope: {}", value),
+                ),
+            )
+        })?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    Ok(scopes)
+}
+
+fn to_response(model: Model) -> ApiKeyResponse {
+    ApiKeyResponse {
+        id: model.id,
+        user_id: model.user_id,
+        name: model.name,
+        key_prefix: model.key_prefix,
+        scopes: model.scopes.as_deref().map(|scopes| {
+            scope::split(scopes)
+                .iter()
+                .map(|scope| scope.as_str().to_string())
+                .collect()
+        }),
+        created_at: model.created_at.to_rfc3339(),
+        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
+        last_used_at: model.last_used_at.map(|t| t.to_rfc3339()),
+        revoked_at: model.revoked_at.map(|t| t.to_rfc3339()),
+    }
+}
@@ -8,10 +8,26 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     common::error::{AppError, ValidationErrors},
-    common::validation::validate_url,
+    common::redirect::validate_redirect_status,
+    common::targeting::{self, TargetingRule},
+    common::validation::{normalize_title, validate_max_visits, validate_url},
     app::AppState,
+    handlers::short_url_handler::{
+        parse_timestamp, parse_update_password, parse_update_targeting, parse_update_url, parse_update_variants,
+        parse_utm, utm_response, variant_responses, UtmRequest, UtmResponse, VariantRequest, VariantResponse,
+    },
+    middleware::auth::{scopes, Authorized},
+    models::{short_url::Model, short_url_schedule, short_url_variant},
+    services::{
+        short_url_service::{ScheduleChanges, ShortUrlChanges},
+        visit_service::{BreakdownRow, StatsBucket},
+    },
 };
 
+const STATUS_DISABLED: i16 = 0;
+const DEFAULT_STATS_DAYS: i64 = 7;
+const MAX_STATS_BUCKETS: i64 = 1000;
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListQuery {
@@ -19,6 +35,7 @@ pub struct ListQuery {
     pub page_size: Option<u64>,
     pub status: Option<i16>,
     pub is_deleted: Option<i16>,
+    pub owner_id: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,6 +45,54 @@ pub struct UpdateRequest {
     pub status: Option<i16>,
     pub is_deleted: Option<i16>,
     pub expires_at: Option<String>,
+    pub password: Option<String>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub max_visits: Option<Option<i64>>,
+    pub activates_at: Option<String>,
+    pub prelaunch_url: Option<String>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub redirect_status: Option<Option<u16>>,
+    pub title: Option<String>,
+    pub forward_query: Option<bool>,
+    pub forward_path: Option<bool>,
+    pub utm: Option<UtmRequest>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub targeting: Option<Option<Vec<TargetingRule>>>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub variants: Option<Option<Vec<VariantRequest>>>,
+    pub sticky_variants: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScheduleRequest {
+    pub url: String,
+    pub applies_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateScheduleRequest {
+    pub url: Option<String>,
+    pub applies_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleResponse {
+    pub id: i64,
+    pub short_url_id: i64,
+    pub url: String,
+    pub applies_at: String,
+    pub applied_at: Option<String>,
+    pub superseded: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleListResponse {
+    pub items: Vec<ScheduleResponse>,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,12 +102,26 @@ pub struct ShortUrlAdminResponse {
     pub short_code: String,
     pub url: String,
     pub status: i16,
+    pub disabled_by_admin: bool,
     pub is_deleted: i16,
     pub created_at: String,
     pub updated_at: String,
     pub deleted_at: Option<String>,
     pub expires_at: Option<String>,
+    pub activates_at: Option<String>,
+    pub prelaunch_url: Option<String>,
+    pub redirect_status: Option<i16>,
+    pub title: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm: UtmResponse,
+    pub targeting: Vec<TargetingRule>,
+    pub variants: Vec<VariantResponse>,
+    pub sticky_variants: bool,
     pub visit_count: i64,
+    pub max_visits: Option<i64>,
+    pub owner_id: Option<i64>,
+    pub password_protected: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,8 +133,47 @@ pub struct ListResponse {
     pub items: Vec<ShortUrlAdminResponse>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    pub id: i64,
+    pub from: String,
+    pub to: String,
+    pub bucket: String,
+    pub total: i64,
+    pub series: Vec<SeriesPoint>,
+    pub referrers: Vec<BreakdownEntry>,
+    pub browsers: Vec<BreakdownEntry>,
+    pub operating_systems: Vec<BreakdownEntry>,
+    pub countries: Vec<BreakdownEntry>,
+    pub variants: Vec<BreakdownEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesPoint {
+    pub start: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakdownEntry {
+    pub value: String,
+    pub clicks: i64,
+}
+
 pub async fn list_short_urls(
     State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
     Query(query): Query<ListQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     let page_size = query.page_size.unwrap_or(20).min(100);
@@ -64,22 +182,16 @@ pub async fn list_short_urls(
 
     let (total, models) = state
         .short_url_service
-        .list_short_urls(page_size, offset, query.status, query.is_deleted)
+        .list_short_urls(page_size, offset, query.status, query.is_deleted, query.owner_id)
         .await?;
+    let ids: Vec<i64> = models.iter().map(|model| model.id).collect();
+    let mut variants = state.short_url_service.list_variants(&ids).await?;
 
     let items = models
         .into_iter()
-        .map(|model| ShortUrlAdminResponse {
-            id: model.id,
-            short_code: model.short_code.unwrap_or_default(),
-            url: model.original_url,
-            status: model.status,
-            is_deleted: model.is_deleted,
-            created_at: model.created_at.to_rfc3339(),
-            updated_at: model.updated_at.to_rfc3339(),
-            deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
-            expires_at: model.expires_at.map(|t| t.to_rfc3339()),
-            visit_count: model.visit_count,
+        .map(|model| {
+            let variants = variants.remove(&model.id).unwrap_or_default();
+            to_admin_response(model, &variants)
         })
         .collect();
 
@@ -93,85 +205,325 @@ pub async fn list_short_urls(
 
 pub async fn get_short_url(
     State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
     let model = state.short_url_service.find_by_id(id).await?;
+    let variants = state.short_url_service.find_variants(&model).await?;
 
-    Ok(Json(ShortUrlAdminResponse {
-        id: model.id,
-        short_code: model.short_code.unwrap_or_default(),
-        url: model.original_url,
-        status: model.status,
-        is_deleted: model.is_deleted,
-        created_at: model.created_at.to_rfc3339(),
-        updated_at: model.updated_at.to_rfc3339(),
-        deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
-        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
-        visit_count: model.visit_count,
-    }))
+    Ok(Json(to_admin_response(model, &variants)))
 }
 
 pub async fn update_short_url(
     State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    let expires_at = parse_update_expires_at(payload.expires_at)?;
+    let expires_at = parse_update_timestamp(payload.expires_at, "expiresAt")?;
+    let activates_at = parse_update_timestamp(payload.activates_at, "activatesAt")?;
+    let prelaunch_url = parse_update_url(payload.prelaunch_url, "prelaunchUrl")?;
+    if let Some(Some(redirect_status)) = payload.redirect_status {
+        validate_redirect_status(redirect_status, "redirectStatus")?;
+    }
+    let title = payload
+        .title
+        .as_deref()
+        .map(|title| normalize_title(title, "title"))
+        .transpose()?;
 
     if let Some(url) = payload.original_url.as_deref() {
         validate_url(url, "originalUrl")?;
     }
+    if let Some(Some(max_visits)) = payload.max_visits {
+        validate_max_visits(max_visits, "maxVisits")?;
+    }
+    let password_hash = parse_update_password(payload.password).await?;
+    let utm = parse_utm(payload.utm)?;
+    let targeting = parse_update_targeting(payload.targeting)?;
+    let variants = parse_update_variants(payload.variants)?;
 
     let updated = state
         .short_url_service
         .update_short_url(
             id,
-            payload.original_url,
-            payload.status,
-            payload.is_deleted,
-            expires_at,
+            ShortUrlChanges {
+                original_url: payload.original_url,
+                status: payload.status,
+                disabled_by_admin: payload.status.map(|status| status == STATUS_DISABLED),
+                is_deleted: payload.is_deleted,
+                expires_at,
+                password_hash,
+                max_visits: payload.max_visits,
+                activates_at,
+                prelaunch_url,
+                redirect_status: payload
+                    .redirect_status
+                    .map(|status| status.map(|status| status as i16)),
+                title,
+                forward_query: payload.forward_query,
+                forward_path: payload.forward_path,
+                utm,
+                targeting,
+                variants,
+                sticky_variants: payload.sticky_variants,
+            },
         )
         .await?;
+    let variants = state.short_url_service.find_variants(&updated).await?;
 
-    Ok((StatusCode::OK, Json(ShortUrlAdminResponse {
-        id: updated.id,
-        short_code: updated.short_code.unwrap_or_default(),
-        url: updated.original_url,
-        status: updated.status,
-        is_deleted: updated.is_deleted,
-        created_at: updated.created_at.to_rfc3339(),
-        updated_at: updated.updated_at.to_rfc3339(),
-        deleted_at: updated.deleted_at.map(|t| t.to_rfc3339()),
-        expires_at: updated.expires_at.map(|t| t.to_rfc3339()),
-        visit_count: updated.visit_count,
-    })))
+    Ok((StatusCode::OK, Json(to_admin_response(updated, &variants))))
 }
 
 pub async fn delete_short_url(
     State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
     state.short_url_service.soft_delete(id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
-fn parse_update_expires_at(
+pub async fn list_schedules(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    state.short_url_service.find_by_id(id).await?;
+    let schedules = state.short_url_service.list_schedules(id).await?;
+
+    Ok(Json(ScheduleListResponse {
+        items: schedules.into_iter().map(to_schedule_response).collect(),
+    }))
+}
+
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CreateScheduleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    validate_url(&payload.url, "url")?;
+    let applies_at = parse_applies_at(payload.applies_at)?;
+
+    let schedule = state
+        .short_url_service
+        .create_schedule(id, payload.url, applies_at)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(to_schedule_response(schedule))))
+}
+
+pub async fn update_schedule(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Path((id, schedule_id)): Path<(i64, i64)>,
+    Json(payload): Json<UpdateScheduleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(url) = payload.url.as_deref() {
+        validate_url(url, "url")?;
+    }
+    let applies_at = payload.applies_at.map(parse_applies_at).transpose()?;
+
+    let schedule = state
+        .short_url_service
+        .update_schedule(
+            id,
+            schedule_id,
+            ScheduleChanges {
+                original_url: payload.url,
+                applies_at,
+            },
+        )
+        .await?;
+
+    Ok(Json(to_schedule_response(schedule)))
+}
+
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Path((id, schedule_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    state.short_url_service.delete_schedule(id, schedule_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_short_url_stats(
+    State(state): State<AppState>,
+    auth: Authorized<scopes::StatsRead>,
+    Path(id): Path<i64>,
+    Query(query): Query<StatsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let bucket = match query.bucket.as_deref().map(str::trim) {
+        None | Some("") => StatsBucket::Day,
+        Some(value) => StatsBucket::parse(value).ok_or_else(|| {
+            AppError::bad_request_with_errors(
+                "bucket is invalid",
+                ValidationErrors::single("bucket", "INVALID_FORMAT", "bucket must be hour, day or week"),
+            )
+        })?,
+    };
+    let to = parse_stats_time(query.to, "to")?.unwrap_or_else(chrono::Utc::now);
+    let from = parse_stats_time(query.from, "from")?
+        .unwrap_or_else(|| to - chrono::Duration::days(DEFAULT_STATS_DAYS));
+
+    if from >= to {
+        return Err(AppError::bad_request_with_errors(
+            "from must be before to",
+            ValidationErrors::single("from", "INVALID_RANGE", "from must be before to"),
+        ));
+    }
+    if (to - from).num_seconds() / bucket.step().num_seconds() > MAX_STATS_BUCKETS {
+        return Err(AppError::bad_request_with_errors(
+            "range is too large for the bucket",
+            ValidationErrors::single(
+                "bucket",
+                "TOO_MANY_BUCKETS",
+                format!("at most {} buckets can be requested", MAX_STATS_BUCKETS),
+            ),
+        ));
+    }
+
+    // Keys without a user own nothing, so they must not match the links nobody owns either.
+    let owner_id = if auth.principal.is_admin() {
+        None
+    } else {
+        Some(auth.principal.require_user()?)
+    };
+    let model = state.short_url_service.find_by_id(id).await?;
+    if owner_id.is_some() && model.owner_id != owner_id {
+        return Err(AppError::forbidden("short url belongs to another user"));
+    }
+    let stats = state
+        .visit_service
+        .visit_stats(model.id, from, to, bucket)
+        .await?;
+
+    Ok(Json(StatsResponse {
+        id: model.id,
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        bucket: bucket.as_str().to_string(),
+        total: stats.total,
+        series: stats
+            .series
+            .into_iter()
+            .map(|(start, clicks)| SeriesPoint {
+                start: start.to_rfc3339(),
+                clicks,
+            })
+            .collect(),
+        referrers: to_breakdown(stats.referrers),
+        browsers: to_breakdown(stats.browsers),
+        operating_systems: to_breakdown(stats.operating_systems),
+        countries: to_breakdown(stats.countries),
+        variants: to_breakdown(stats.variants),
+    }))
+}
+
+fn to_admin_response(model: Model, variants: &[short_url_variant::Model]) -> ShortUrlAdminResponse {
+    let utm = utm_response(&model);
+    let targeting = targeting::rules(&model);
+    ShortUrlAdminResponse {
+        id: model.id,
+        short_code: model.short_code,
+        url: model.original_url,
+        status: model.status,
+        disabled_by_admin: model.disabled_by_admin,
+        is_deleted: model.is_deleted,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+        deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
+        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
+        activates_at: model.activates_at.map(|t| t.to_rfc3339()),
+        prelaunch_url: model.prelaunch_url,
+        redirect_status: model.redirect_status,
+        title: model.title,
+        forward_query: model.forward_query,
+        forward_path: model.forward_path,
+        utm,
+        targeting,
+        variants: variant_responses(variants),
+        sticky_variants: model.sticky_variants,
+        visit_count: model.visit_count,
+        max_visits: model.max_visits,
+        owner_id: model.owner_id,
+        password_protected: model.password_hash.is_some(),
+    }
+}
+
+fn to_breakdown(rows: Vec<BreakdownRow>) -> Vec<BreakdownEntry> {
+    rows.into_iter()
+        .map(|row| BreakdownEntry {
+            value: row.value,
+            clicks: row.clicks,
+        })
+        .collect()
+}
+
+fn parse_stats_time(
     input: Option<String>,
-) -> Result<Option<Option<sea_orm::prelude::DateTimeWithTimeZone>>, AppError> {
+    field_name: &str,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, AppError> {
     let Some(value) = input else {
         return Ok(None);
     };
     let trimmed = value.trim();
     if trimmed.is_empty() {
-        return Ok(Some(None));
+        return Ok(None);
     }
 
     let parsed = chrono::DateTime::parse_from_rfc3339(trimmed).map_err(|_| {
         AppError::bad_request_with_errors(
-            "expires_at is invalid",
-            ValidationErrors::single("expiresAt", "INVALID_FORMAT", "expires_at must be RFC3339"),
+            format!("{} is invalid", field_name),
+            ValidationErrors::single(field_name, "INVALID_FORMAT", format!("{} must be RFC3339", field_name)),
+        )
+    })?;
+
+    Ok(Some(parsed.with_timezone(&chrono::Utc)))
+}
+
+// Schedules always need a time, and one that has already passed would apply on the next tick.
+fn parse_applies_at(input: String) -> Result<sea_orm::prelude::DateTimeWithTimeZone, AppError> {
+    let applies_at = parse_timestamp(Some(input), "appliesAt")?.ok_or_else(|| {
+        AppError::bad_request_with_errors(
+            "appliesAt is required",
+            ValidationErrors::single("appliesAt", "REQUIRED", "appliesAt is required"),
         )
     })?;
+    if applies_at <= chrono::Utc::now().fixed_offset() {
+        return Err(AppError::bad_request_with_errors(
+            "appliesAt is invalid",
+            ValidationErrors::single("appliesAt", "INVALID_RANGE", "appliesAt must be in the future"),
+        ));
+    }
+    Ok(applies_at)
+}
+
+fn to_schedule_response(schedule: short_url_schedule::Model) -> ScheduleResponse {
+    ScheduleResponse {
+        id: schedule.id,
+        short_url_id: schedule.short_url_id,
+        url: schedule.original_url,
+        applies_at: schedule.applies_at.to_rfc3339(),
+        applied_at: schedule.applied_at.map(|applied_at| applied_at.to_rfc3339()),
+        superseded: schedule.superseded,
+        created_at: schedule.created_at.to_rfc3339(),
+    }
+}
+
+// An empty string clears the timestamp.
+pub(crate) fn parse_update_timestamp(
+    input: Option<String>,
+    field_name: &str,
+) -> Result<Option<Option<sea_orm::prelude::DateTimeWithTimeZone>>, AppError> {
+    let Some(value) = input else {
+        return Ok(None);
+    };
+    if value.trim().is_empty() {
+        return Ok(Some(None));
+    }
 
-    Ok(Some(Some(parsed)))
+    parse_timestamp(Some(value), field_name).map(Some)
 }
@@ -0,0 +1,75 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppState,
+    middleware::auth::{scopes, Authorized},
+    common::error::{AppError, ValidationErrors},
+    models::user::Model,
+    services::user_service::UserRole,
+};
+
+const USERNAME_MAX_LEN: usize = 64;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserResponse {
+    pub id: i64,
+    pub username: String,
+    pub role: String,
+    pub created_at: String,
+}
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let username = payload.username.trim().to_string();
+    if username.is_empty() || username.chars().count() > USERNAME_MAX_LEN {
+        return Err(AppError::bad_request_with_errors(
+            "username is invalid",
+            ValidationErrors::single(
+                "username",
+                "INVALID_LENGTH",
+                format!("username must be between 1 and {} characters", USERNAME_MAX_LEN),
+            ),
+        ));
+    }
+    let role = match payload.role.as_deref().map(str::trim) {
+        None | Some("") => UserRole::User,
+        Some(value) => UserRole::parse(value).ok_or_else(|| {
+            AppError::bad_request_with_errors(
+                "role is invalid",
+                ValidationErrors::single("role", "INVALID_VALUE", "role must be admin or user"),
+            )
+        })?,
+    };
+
+    let model = state.user_service.create_user(username, role).await?;
+    Ok((StatusCode::CREATED, Json(to_response(model))))
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    _auth: Authorized<scopes::Admin>,
+) -> Result<impl IntoResponse, AppError> {
+    let users = state.user_service.list_users().await?;
+    Ok(Json(users.into_iter().map(to_response).collect::<Vec<_>>()))
+}
+
+fn to_response(model: Model) -> UserResponse {
+    UserResponse {
+        id: model.id,
+        username: model.username,
+        role: model.role,
+        created_at: model.created_at.to_rfc3339(),
+    }
+}
@@ -1,2 +1,4 @@
 pub mod short_url_handler;
 pub mod admin_short_url_handler;
+pub mod admin_api_key_handler;
+pub mod admin_user_handler;
@@ -1,21 +1,164 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
-    Json,
+    extract::{Path, Query, RawQuery, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{Html, IntoResponse, Redirect, Response},
+    Form, Json,
 };
 use serde::{Deserialize, Serialize};
 use crate::{
+    common::client_info::ClientInfo,
+    common::destination,
     common::error::{AppError, ValidationErrors},
-    common::validation::validate_url,
+    common::password,
+    common::redirect::{redirect, validate_redirect_status},
+    common::targeting::{self, TargetingRule},
+    common::variant,
+    common::validation::{
+        normalize_targeting_rules, normalize_title, normalize_utm_value, validate_activation_window, validate_alias, validate_max_visits,
+        validate_password, validate_url,
+    },
     app::AppState,
+    handlers::admin_short_url_handler::parse_update_timestamp,
+    models::{short_url::Model, short_url_variant},
+    middleware::auth::{scopes, Authorized},
+    services::short_url_service::{visit_limit_reached, NewShortUrl, ShortUrlChanges, UtmChanges, VariantInput},
+    utils::qr::{self, QrOptions},
+    views,
 };
 
+const STATUS_VALUES: [i16; 2] = [0, 1];
+const STATUS_ACTIVE: i16 = 1;
+const QR_DEFAULT_SIZE: u32 = 256;
+const QR_SIZE_RANGE: std::ops::RangeInclusive<u32> = 64..=1024;
+const QR_DEFAULT_MARGIN: u32 = 4;
+const QR_MAX_MARGIN: u32 = 16;
+const MAX_VARIANTS: usize = 20;
+const VARIANT_WEIGHT_RANGE: std::ops::RangeInclusive<i32> = 0..=10_000;
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateShortUrlRequest {
     pub url: String,
     pub expires_at: Option<String>,
+    pub alias: Option<String>,
+    pub password: Option<String>,
+    pub max_visits: Option<i64>,
+    pub activates_at: Option<String>,
+    pub prelaunch_url: Option<String>,
+    pub redirect_status: Option<u16>,
+    pub title: Option<String>,
+    pub forward_query: Option<bool>,
+    pub forward_path: Option<bool>,
+    pub utm: Option<UtmRequest>,
+    pub targeting: Option<Vec<TargetingRule>>,
+    pub variants: Option<Vec<VariantRequest>>,
+    pub sticky_variants: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateShortUrlRequest {
+    pub original_url: Option<String>,
+    pub status: Option<i16>,
+    pub expires_at: Option<String>,
+    pub password: Option<String>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub max_visits: Option<Option<i64>>,
+    pub activates_at: Option<String>,
+    pub prelaunch_url: Option<String>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub redirect_status: Option<Option<u16>>,
+    pub title: Option<String>,
+    pub forward_query: Option<bool>,
+    pub forward_path: Option<bool>,
+    pub utm: Option<UtmRequest>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub targeting: Option<Option<Vec<TargetingRule>>>,
+    #[serde(default, deserialize_with = "crate::common::nullable::deserialize")]
+    pub variants: Option<Option<Vec<VariantRequest>>>,
+    pub sticky_variants: Option<bool>,
+}
+
+// Shared by create and both update endpoints; `""` clears a tag on update.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtmRequest {
+    pub source: Option<String>,
+    pub medium: Option<String>,
+    pub campaign: Option<String>,
+    pub term: Option<String>,
+    pub content: Option<String>,
+    #[serde(rename = "override")]
+    pub override_existing: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtmResponse {
+    pub source: Option<String>,
+    pub medium: Option<String>,
+    pub campaign: Option<String>,
+    pub term: Option<String>,
+    pub content: Option<String>,
+    #[serde(rename = "override")]
+    pub override_existing: bool,
+}
+
+// Updates list every variant to keep; `id` keeps an existing one, e.g. to change its weight.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantRequest {
+    pub id: Option<i64>,
+    pub url: String,
+    pub weight: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantResponse {
+    pub id: i64,
+    pub url: String,
+    pub weight: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QrQuery {
+    pub format: Option<String>,
+    pub size: Option<u32>,
+    pub margin: Option<u32>,
+    pub ec_level: Option<String>,
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+}
+
+// Serves both `/{code}` and `/{code}/{*rest}`.
+#[derive(Debug, Deserialize)]
+pub struct RedirectPath {
+    pub code: String,
+    pub rest: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnlockForm {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListOwnQuery {
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+    pub status: Option<i16>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortUrlListResponse {
+    pub total: u64,
+    pub page: u64,
+    pub page_size: u64,
+    pub items: Vec<ShortUrlResponse>,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,38 +173,202 @@ pub struct ShortUrlResponse {
     pub updated_at: String,
     pub deleted_at: Option<String>,
     pub expires_at: Option<String>,
+    pub activates_at: Option<String>,
+    pub prelaunch_url: Option<String>,
+    pub redirect_status: Option<i16>,
+    pub title: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm: UtmResponse,
+    pub targeting: Vec<TargetingRule>,
+    pub variants: Vec<VariantResponse>,
+    pub sticky_variants: bool,
     pub visit_count: i64,
+    pub max_visits: Option<i64>,
+    pub password_protected: bool,
     pub short_url: Option<String>,
 }
 
 pub async fn create_short_url(
     State(state): State<AppState>,
+    auth: Option<Authorized<scopes::LinksCreate>>,
     Json(payload): Json<CreateShortUrlRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     validate_url(&payload.url, "url")?;
-    let expires_at = parse_expires_at(payload.expires_at)?;
+    let expires_at = parse_timestamp(payload.expires_at, "expiresAt")?;
+    let activates_at = parse_timestamp(payload.activates_at, "activatesAt")?;
+    validate_activation_window(activates_at, expires_at)?;
+    let prelaunch_url = parse_update_url(payload.prelaunch_url, "prelaunchUrl")?.flatten();
+    if let Some(redirect_status) = payload.redirect_status {
+        validate_redirect_status(redirect_status, "redirectStatus")?;
+    }
+    let title = match payload.title.as_deref() {
+        Some(title) => normalize_title(title, "title")?,
+        None => None,
+    };
+    let alias = payload
+        .alias
+        .map(|alias| alias.trim().to_string())
+        .filter(|alias| !alias.is_empty());
+    if let Some(alias) = alias.as_deref() {
+        validate_alias(alias, "alias")?;
+    }
+    if let Some(max_visits) = payload.max_visits {
+        validate_max_visits(max_visits, "maxVisits")?;
+    }
+    let password_hash = parse_update_password(payload.password).await?.flatten();
+    let utm = parse_utm(payload.utm)?;
+    let targeting = normalize_targeting_rules(payload.targeting.unwrap_or_default(), "targeting")?;
+    let variants = parse_variants(payload.variants.unwrap_or_default())?;
 
     let model = state
         .short_url_service
-        .create_short_url(payload.url, expires_at)
+        .create_short_url(NewShortUrl {
+            original_url: payload.url,
+            expires_at,
+            alias,
+            owner_id: auth.and_then(|auth| auth.principal.user_id),
+            password_hash,
+            max_visits: payload.max_visits,
+            activates_at,
+            prelaunch_url,
+            redirect_status: payload.redirect_status.map(|status| status as i16),
+            title,
+            forward_query: payload.forward_query.unwrap_or(false),
+            forward_path: payload.forward_path.unwrap_or(false),
+            utm,
+            targeting,
+            variants,
+            sticky_variants: payload.sticky_variants.unwrap_or(false),
+        })
         .await?;
-    let code = model.short_code.clone().unwrap_or_else(|| "".to_string());
+    let variants = state.short_url_service.find_variants(&model).await?;
 
-    let response = ShortUrlResponse {
-        id: model.id,
-        short_code: code.clone(),
-        url: model.original_url,
-        status: model.status,
-        is_deleted: model.is_deleted,
-        created_at: model.created_at.to_rfc3339(),
-        updated_at: model.updated_at.to_rfc3339(),
-        deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
-        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
-        visit_count: model.visit_count,
-        short_url: build_short_url(state.base_url.as_deref(), &code),
-    };
+    Ok((
+        StatusCode::CREATED,
+        Json(to_response(model, &variants, state.base_url.as_deref())),
+    ))
+}
+
+pub async fn list_own_short_urls(
+    State(state): State<AppState>,
+    auth: Authorized<scopes::LinksRead>,
+    Query(query): Query<ListOwnQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let owner_id = auth.principal.require_user()?;
+    let page_size = query.page_size.unwrap_or(20).min(100);
+    let page = query.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * page_size;
 
-    Ok((StatusCode::CREATED, Json(response)))
+    let (total, models) = state
+        .short_url_service
+        .list_short_urls(page_size, offset, query.status, Some(0), Some(owner_id))
+        .await?;
+    let ids: Vec<i64> = models.iter().map(|model| model.id).collect();
+    let mut variants = state.short_url_service.list_variants(&ids).await?;
+
+    Ok(Json(ShortUrlListResponse {
+        total,
+        page,
+        page_size,
+        items: models
+            .into_iter()
+            .map(|model| {
+                let variants = variants.remove(&model.id).unwrap_or_default();
+                to_response(model, &variants, state.base_url.as_deref())
+            })
+            .collect(),
+    }))
+}
+
+pub async fn update_own_short_url(
+    State(state): State<AppState>,
+    auth: Authorized<scopes::LinksWrite>,
+    Path(code): Path<String>,
+    Json(payload): Json<UpdateShortUrlRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let owner_id = auth.principal.require_user()?;
+    let expires_at = parse_update_timestamp(payload.expires_at, "expiresAt")?;
+    let activates_at = parse_update_timestamp(payload.activates_at, "activatesAt")?;
+    let prelaunch_url = parse_update_url(payload.prelaunch_url, "prelaunchUrl")?;
+    if let Some(Some(redirect_status)) = payload.redirect_status {
+        validate_redirect_status(redirect_status, "redirectStatus")?;
+    }
+    let title = payload
+        .title
+        .as_deref()
+        .map(|title| normalize_title(title, "title"))
+        .transpose()?;
+    if let Some(url) = payload.original_url.as_deref() {
+        validate_url(url, "originalUrl")?;
+    }
+    if let Some(status) = payload.status
+        && !STATUS_VALUES.contains(&status)
+    {
+        return Err(AppError::bad_request_with_errors(
+            "status is invalid",
+            ValidationErrors::single("status", "INVALID_VALUE", "status must be 0 or 1"),
+        ));
+    }
+    if let Some(Some(max_visits)) = payload.max_visits {
+        validate_max_visits(max_visits, "maxVisits")?;
+    }
+
+    let password_hash = parse_update_password(payload.password).await?;
+    let utm = parse_utm(payload.utm)?;
+    let targeting = parse_update_targeting(payload.targeting)?;
+    let variants = parse_update_variants(payload.variants)?;
+
+    let model = state
+        .short_url_service
+        .find_owned_by_code(&code, owner_id)
+        .await?;
+    if payload.status == Some(STATUS_ACTIVE) && model.disabled_by_admin {
+        return Err(AppError::forbidden("short url was disabled by an administrator"));
+    }
+    let updated = state
+        .short_url_service
+        .update_short_url(
+            model.id,
+            ShortUrlChanges {
+                original_url: payload.original_url,
+                status: payload.status,
+                expires_at,
+                password_hash,
+                max_visits: payload.max_visits,
+                activates_at,
+                prelaunch_url,
+                redirect_status: payload
+                    .redirect_status
+                    .map(|status| status.map(|status| status as i16)),
+                title,
+                forward_query: payload.forward_query,
+                forward_path: payload.forward_path,
+                utm,
+                targeting,
+                variants,
+                sticky_variants: payload.sticky_variants,
+                ..Default::default()
+            },
+        )
+        .await?;
+    let variants = state.short_url_service.find_variants(&updated).await?;
+
+    Ok(Json(to_response(updated, &variants, state.base_url.as_deref())))
+}
+
+pub async fn delete_own_short_url(
+    State(state): State<AppState>,
+    auth: Authorized<scopes::LinksWrite>,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let owner_id = auth.principal.require_user()?;
+    let model = state
+        .short_url_service
+        .find_owned_by_code(&code, owner_id)
+        .await?;
+    state.short_url_service.soft_delete(model.id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn get_short_url(
@@ -70,39 +377,180 @@ pub async fn get_short_url(
 ) -> Result<impl IntoResponse, AppError> {
     let model = state.short_url_service.find_by_code(&code).await?;
     ensure_not_expired(&model)?;
+    ensure_active(&model)?;
+    ensure_visits_left(&model)?;
+    if model.password_hash.is_some() {
+        return Err(AppError::forbidden("short url is password protected"));
+    }
+    let variants = state.short_url_service.find_variants(&model).await?;
 
-    let response = ShortUrlResponse {
-        id: model.id,
-        short_code: code,
-        url: model.original_url,
-        status: model.status,
-        is_deleted: model.is_deleted,
-        created_at: model.created_at.to_rfc3339(),
-        updated_at: model.updated_at.to_rfc3339(),
-        deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
-        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
-        visit_count: model.visit_count,
-        short_url: build_short_url(
-            state.base_url.as_deref(),
-            &model.short_code.clone().unwrap_or_default(),
-        ),
+    Ok(Json(to_response(model, &variants, state.base_url.as_deref())))
+}
+
+// Links that are not active yet still get a code, so campaigns can go to print before launch.
+pub async fn get_short_url_qr(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    Query(query): Query<QrQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let svg = match query.format.as_deref().map(str::trim) {
+        Some("svg") => true,
+        Some("png") => false,
+        Some(_) => return Err(qr_invalid("format", "format must be png or svg")),
+        None => headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("image/svg+xml")),
     };
+    let options = qr_options(&query)?;
+    // Checked once at startup too; the request Host is not trusted to name the printed URL.
+    let short_url = build_short_url(state.base_url.as_deref(), &code).ok_or_else(|| {
+        AppError::service_unavailable("qr codes are unavailable until server.base_url is configured")
+    })?;
 
-    Ok(Json(response))
+    let model = state.short_url_service.find_by_code(&code).await?;
+    ensure_not_expired(&model)?;
+
+    // Rasterising a large PNG takes a while, so it stays off the async workers.
+    let (content_type, body) = tokio::task::spawn_blocking(move || {
+        if svg {
+            qr::render_svg(&short_url, &options).map(|body| ("image/svg+xml", body.into_bytes()))
+        } else {
+            qr::render_png(&short_url, &options).map(|body| ("image/png", body))
+        }
+    })
+    .await
+    .map_err(|err| AppError::internal(format!("failed to render qr code: {err}")))?
+    .map_err(|err| AppError::internal(format!("failed to render qr code: {err}")))?;
+    let cache_control = (header::CACHE_CONTROL, "public, max-age=86400");
+    Ok(([(header::CONTENT_TYPE, content_type), cache_control], body).into_response())
 }
 
 pub async fn redirect_short_url(
     State(state): State<AppState>,
-    Path(code): Path<String>,
-) -> Result<impl IntoResponse, AppError> {
+    Path(RedirectPath { code, rest }): Path<RedirectPath>,
+    RawQuery(query): RawQuery,
+    uri: Uri,
+    headers: HeaderMap,
+    client: ClientInfo,
+) -> Result<Response, AppError> {
+    // The router cannot match a suffix inside a segment, so `/{code}+` arrives here.
+    if rest.is_none()
+        && let Some(code) = code.strip_suffix('+')
+    {
+        return preview_short_url(&state, code).await;
+    }
+
     let model = state.short_url_service.find_by_code(&code).await?;
+    ensure_path_allowed(&model, rest.as_deref())?;
     ensure_not_expired(&model)?;
+    if let Err(err) = ensure_active(&model) {
+        // Pre-launch traffic is not a visit to the link itself.
+        return match &model.prelaunch_url {
+            Some(prelaunch_url) => Ok(Redirect::temporary(prelaunch_url).into_response()),
+            None => Err(err),
+        };
+    }
+    ensure_visits_left(&model)?;
+    if model.password_hash.is_some() {
+        return Ok(Html(views::password::render(&form_action(&uri), None)).into_response());
+    }
 
-    state
-        .short_url_service
-        .increment_visit_count(model.id)
+    let destination = destination_for(&state, &model, &client, &headers, rest.as_deref(), query.as_deref()).await?;
+    record_visit(&state, &model, destination.variant_id, client).await?;
+    let status = model
+        .redirect_status
+        .and_then(|status| StatusCode::from_u16(status as u16).ok())
+        .unwrap_or(state.default_redirect_status);
+    Ok(destination.apply(redirect(status, &destination.location)))
+}
+
+// Shows where a link leads without following it; `Continue` goes through the normal redirect.
+async fn preview_short_url(state: &AppState, code: &str) -> Result<Response, AppError> {
+    let model = state.short_url_service.find_by_code(code).await?;
+    ensure_not_expired(&model)?;
+    ensure_active(&model)?;
+    ensure_visits_left(&model)?;
+    if model.password_hash.is_some() {
+        return Ok(Html(views::password::render(&format!("/{}", code), None)).into_response());
+    }
+
+    let page = views::preview::render(
+        code,
+        model.title.as_deref(),
+        &model.original_url,
+        &model.created_at.format("%Y-%m-%d").to_string(),
+    );
+    Ok(Html(page).into_response())
+}
+
+// Receives the password form; the visit is only recorded once the password matches.
+pub async fn unlock_short_url(
+    State(state): State<AppState>,
+    Path(RedirectPath { code, rest }): Path<RedirectPath>,
+    RawQuery(query): RawQuery,
+    uri: Uri,
+    headers: HeaderMap,
+    client: ClientInfo,
+    Form(form): Form<UnlockForm>,
+) -> Result<Response, AppError> {
+    let model = state.short_url_service.find_by_code(&code).await?;
+    ensure_path_allowed(&model, rest.as_deref())?;
+    ensure_not_expired(&model)?;
+    ensure_active(&model)?;
+    ensure_visits_left(&model)?;
+
+    if let Some(password_hash) = model.password_hash.clone() {
+        let client_ip = client.ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+        let attempt_key = format!("link:{}:{}", model.id, client_ip);
+        let unlocked = password::verify_throttled(
+            state.password_limiter.as_ref(),
+            &attempt_key,
+            password_hash,
+            form.password,
+        )
         .await?;
-    Ok(Redirect::temporary(&model.original_url))
+        if !unlocked {
+            let page = views::password::render(&form_action(&uri), Some("Incorrect password."));
+            return Ok((StatusCode::FORBIDDEN, Html(page)).into_response());
+        }
+    }
+
+    let destination = destination_for(&state, &model, &client, &headers, rest.as_deref(), query.as_deref()).await?;
+    record_visit(&state, &model, destination.variant_id, client).await?;
+    Ok(destination.apply(Redirect::to(&destination.location).into_response()))
+}
+
+fn to_response(model: Model, variants: &[short_url_variant::Model], base_url: Option<&str>) -> ShortUrlResponse {
+    let utm = utm_response(&model);
+    let targeting = targeting::rules(&model);
+    let code = model.short_code;
+    ShortUrlResponse {
+        id: model.id,
+        short_url: build_short_url(base_url, &code),
+        short_code: code,
+        url: model.original_url,
+        status: model.status,
+        is_deleted: model.is_deleted,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+        deleted_at: model.deleted_at.map(|t| t.to_rfc3339()),
+        expires_at: model.expires_at.map(|t| t.to_rfc3339()),
+        activates_at: model.activates_at.map(|t| t.to_rfc3339()),
+        prelaunch_url: model.prelaunch_url,
+        redirect_status: model.redirect_status,
+        title: model.title,
+        forward_query: model.forward_query,
+        forward_path: model.forward_path,
+        utm,
+        targeting,
+        variants: variant_responses(variants),
+        sticky_variants: model.sticky_variants,
+        visit_count: model.visit_count,
+        max_visits: model.max_visits,
+        password_protected: model.password_hash.is_some(),
+    }
 }
 
 fn build_short_url(base_url: Option<&str>, code: &str) -> Option<String> {
@@ -113,7 +561,10 @@ fn build_short_url(base_url: Option<&str>, code: &str) -> Option<String> {
     Some(format!("{}/{}", base, code))
 }
 
-fn parse_expires_at(input: Option<String>) -> Result<Option<sea_orm::prelude::DateTimeWithTimeZone>, AppError> {
+pub(crate) fn parse_timestamp(
+    input: Option<String>,
+    field_name: &str,
+) -> Result<Option<sea_orm::prelude::DateTimeWithTimeZone>, AppError> {
     let Some(value) = input else {
         return Ok(None);
     };
@@ -123,16 +574,292 @@ fn parse_expires_at(input: Option<String>) -> Result<Option<sea_orm::prelude::Da
     }
 
     let parsed = chrono::DateTime::parse_from_rfc3339(trimmed).map_err(|_| {
+        let name = snake_case(field_name);
         AppError::bad_request_with_errors(
-            "expires_at is invalid",
-            ValidationErrors::single("expiresAt", "INVALID_FORMAT", "expires_at must be RFC3339"),
+            format!("{} is invalid", name),
+            ValidationErrors::single(field_name, "INVALID_FORMAT", format!("{} must be RFC3339", name)),
         )
     })?;
 
     Ok(Some(parsed))
 }
 
-fn ensure_not_expired(model: &crate::models::short_url::Model) -> Result<(), AppError> {
+// Messages have always spelled timestamp fields the way the columns do, e.g. `expires_at`.
+fn snake_case(field_name: &str) -> String {
+    let mut name = String::with_capacity(field_name.len() + 2);
+    for ch in field_name.chars() {
+        if ch.is_ascii_uppercase() {
+            name.push('_');
+            name.push(ch.to_ascii_lowercase());
+        } else {
+            name.push(ch);
+        }
+    }
+    name
+}
+
+// `Some(None)` removes the password; an empty string is treated the same way.
+pub(crate) async fn parse_update_password(input: Option<String>) -> Result<Option<Option<String>>, AppError> {
+    let Some(value) = input else {
+        return Ok(None);
+    };
+    if value.is_empty() {
+        return Ok(Some(None));
+    }
+
+    validate_password(&value, "password")?;
+    Ok(Some(Some(password::hash(value).await?)))
+}
+
+// Links without a visit limit are counted in batches; limited ones must be claimed first.
+async fn record_visit(
+    state: &AppState,
+    model: &Model,
+    variant_id: Option<i64>,
+    client: ClientInfo,
+) -> Result<(), AppError> {
+    if model.max_visits.is_none() {
+        state.visit_recorder.record(model.id, variant_id, client);
+        return Ok(());
+    }
+
+    state.short_url_service.claim_visit(model.id).await?;
+    state.visit_recorder.record_claimed(model.id, variant_id, client);
+    Ok(())
+}
+
+fn qr_options(query: &QrQuery) -> Result<QrOptions, AppError> {
+    let size = query.size.unwrap_or(QR_DEFAULT_SIZE);
+    if !QR_SIZE_RANGE.contains(&size) {
+        return Err(qr_invalid(
+            "size",
+            &format!("size must be between {} and {}", QR_SIZE_RANGE.start(), QR_SIZE_RANGE.end()),
+        ));
+    }
+    let margin = query.margin.unwrap_or(QR_DEFAULT_MARGIN);
+    if margin > QR_MAX_MARGIN {
+        return Err(qr_invalid("margin", &format!("margin must be at most {}", QR_MAX_MARGIN)));
+    }
+    let ec_level = match query.ec_level.as_deref().map(str::trim) {
+        None | Some("M") | Some("m") => qrcode::EcLevel::M,
+        Some("L") | Some("l") => qrcode::EcLevel::L,
+        Some("Q") | Some("q") => qrcode::EcLevel::Q,
+        Some("H") | Some("h") => qrcode::EcLevel::H,
+        Some(_) => return Err(qr_invalid("ecLevel", "ecLevel must be L, M, Q or H")),
+    };
+    let color = |input: Option<&str>, default: [u8; 3], field_name: &str| match input {
+        None => Ok(default),
+        Some(value) => qr::parse_color(value)
+            .ok_or_else(|| qr_invalid(field_name, &format!("{} must be a hex colour", field_name))),
+    };
+
+    Ok(QrOptions {
+        size,
+        margin,
+        ec_level,
+        foreground: color(query.fg.as_deref(), [0, 0, 0], "fg")?,
+        background: color(query.bg.as_deref(), [255, 255, 255], "bg")?,
+    })
+}
+
+fn qr_invalid(field_name: &str, message: &str) -> AppError {
+    AppError::bad_request_with_errors(
+        format!("{} is invalid", field_name),
+        ValidationErrors::single(field_name, "INVALID_VALUE", message),
+    )
+}
+
+// An empty string clears the URL.
+pub(crate) fn parse_update_url(input: Option<String>, field_name: &str) -> Result<Option<Option<String>>, AppError> {
+    let Some(value) = input else {
+        return Ok(None);
+    };
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Ok(Some(None));
+    }
+
+    validate_url(trimmed, field_name)?;
+    Ok(Some(Some(trimmed.to_string())))
+}
+
+pub(crate) fn parse_utm(input: Option<UtmRequest>) -> Result<UtmChanges, AppError> {
+    let Some(input) = input else {
+        return Ok(UtmChanges::default());
+    };
+    let tag = |value: Option<String>, field: &str| {
+        value
+            .as_deref()
+            .map(|value| normalize_utm_value(value, field))
+            .transpose()
+    };
+    Ok(UtmChanges {
+        source: tag(input.source, "utm.source")?,
+        medium: tag(input.medium, "utm.medium")?,
+        campaign: tag(input.campaign, "utm.campaign")?,
+        term: tag(input.term, "utm.term")?,
+        content: tag(input.content, "utm.content")?,
+        override_existing: input.override_existing,
+    })
+}
+
+pub(crate) fn utm_response(model: &Model) -> UtmResponse {
+    UtmResponse {
+        source: model.utm_source.clone(),
+        medium: model.utm_medium.clone(),
+        campaign: model.utm_campaign.clone(),
+        term: model.utm_term.clone(),
+        content: model.utm_content.clone(),
+        override_existing: model.utm_override,
+    }
+}
+
+pub(crate) fn parse_update_targeting(
+    input: Option<Option<Vec<TargetingRule>>>,
+) -> Result<Option<Vec<TargetingRule>>, AppError> {
+    input
+        .map(|rules| normalize_targeting_rules(rules.unwrap_or_default(), "targeting"))
+        .transpose()
+}
+
+pub(crate) fn parse_variants(input: Vec<VariantRequest>) -> Result<Vec<VariantInput>, AppError> {
+    if input.len() > MAX_VARIANTS {
+        return Err(AppError::bad_request_with_errors(
+            "variants has too many entries",
+            ValidationErrors::single(
+                "variants",
+                "INVALID_LENGTH",
+                format!("variants must have at most {} entries", MAX_VARIANTS),
+            ),
+        ));
+    }
+    if !input.is_empty() && input.iter().all(|variant| variant.weight == 0) {
+        return Err(AppError::bad_request_with_errors(
+            "variants weights are invalid",
+            ValidationErrors::single("variants", "INVALID_VALUE", "at least one variant needs a weight above 0"),
+        ));
+    }
+
+    input
+        .into_iter()
+        .enumerate()
+        .map(|(index, variant)| {
+            validate_url(&variant.url, &format!("variants[{}].url", index))?;
+            if !VARIANT_WEIGHT_RANGE.contains(&variant.weight) {
+                let field = format!("variants[{}].weight", index);
+                return Err(AppError::bad_request_with_errors(
+                    format!("{} is invalid", field),
+                    ValidationErrors::single(
+                        &field,
+                        "INVALID_RANGE",
+                        format!(
+                            "{} must be between {} and {}",
+                            field,
+                            VARIANT_WEIGHT_RANGE.start(),
+                            VARIANT_WEIGHT_RANGE.end()
+                        ),
+                    ),
+                ));
+            }
+            Ok(VariantInput {
+                id: variant.id,
+                url: variant.url,
+                weight: variant.weight,
+            })
+        })
+        .collect()
+}
+
+// `null` and `[]` both remove every variant.
+pub(crate) fn parse_update_variants(
+    input: Option<Option<Vec<VariantRequest>>>,
+) -> Result<Option<Vec<VariantInput>>, AppError> {
+    input
+        .map(|variants| parse_variants(variants.unwrap_or_default()))
+        .transpose()
+}
+
+pub(crate) fn variant_responses(variants: &[short_url_variant::Model]) -> Vec<VariantResponse> {
+    variants
+        .iter()
+        .map(|variant| VariantResponse {
+            id: variant.id,
+            url: variant.url.clone(),
+            weight: variant.weight,
+        })
+        .collect()
+}
+
+struct Destination {
+    location: String,
+    variant_id: Option<i64>,
+    sticky_cookie: Option<HeaderValue>,
+}
+
+impl Destination {
+    fn apply(&self, mut response: Response) -> Response {
+        if let Some(cookie) = &self.sticky_cookie {
+            response.headers_mut().append(header::SET_COOKIE, cookie.clone());
+        }
+        response
+    }
+}
+
+// A matching targeting rule wins over variants, and both over `original_url`; passthrough and UTM
+// tags apply to whichever is chosen.
+async fn destination_for(
+    state: &AppState,
+    model: &Model,
+    client: &ClientInfo,
+    headers: &HeaderMap,
+    rest: Option<&str>,
+    query: Option<&str>,
+) -> Result<Destination, AppError> {
+    if let Some(target) = targeting::resolve(model, client) {
+        return Ok(Destination {
+            location: destination::build(&target, model, rest, query),
+            variant_id: None,
+            sticky_cookie: None,
+        });
+    }
+
+    let variants = state.short_url_service.find_variants(model).await?;
+    let sticky = model
+        .sticky_variants
+        .then(|| variant::sticky_variant(headers))
+        .flatten();
+    let Some(chosen) = variant::pick(&variants, sticky) else {
+        return Ok(Destination {
+            location: destination::build(&model.original_url, model, rest, query),
+            variant_id: None,
+            sticky_cookie: None,
+        });
+    };
+    Ok(Destination {
+        location: destination::build(&chosen.url, model, rest, query),
+        variant_id: Some(chosen.id),
+        sticky_cookie: (model.sticky_variants && sticky != Some(chosen.id))
+            .then(|| variant::sticky_cookie(&model.short_code, chosen.id))
+            .flatten(),
+    })
+}
+
+fn form_action(uri: &Uri) -> String {
+    uri.path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| uri.path().to_string())
+}
+
+// Extra path segments only resolve for links that forward them, and never with dot segments.
+fn ensure_path_allowed(model: &Model, rest: Option<&str>) -> Result<(), AppError> {
+    if rest.is_some_and(|rest| !model.forward_path || !destination::is_forwardable_path(rest)) {
+        return Err(AppError::not_found("short url not found"));
+    }
+    Ok(())
+}
+
+#[allow(clippy::collapsible_if)]
+fn ensure_not_expired(model: &Model) -> Result<(), AppError> {
     if let Some(expires_at) = model.expires_at {
         if expires_at <= chrono::Utc::now().fixed_offset() {
             return Err(AppError::gone("short url expired"));
@@ -140,3 +867,42 @@ fn ensure_not_expired(model: &crate::models::short_url::Model) -> Result<(), App
     }
     Ok(())
 }
+
+// Fast path on the cached count; `claim_visit` makes the authoritative check.
+fn ensure_visits_left(model: &Model) -> Result<(), AppError> {
+    if let Some(max_visits) = model.max_visits
+        && model.visit_count >= max_visits
+    {
+        return Err(visit_limit_reached());
+    }
+    Ok(())
+}
+
+fn ensure_active(model: &Model) -> Result<(), AppError> {
+    if let Some(activates_at) = model.activates_at
+        && activates_at > chrono::Utc::now().fixed_offset()
+    {
+        return Err(AppError::not_yet_active(
+            "short url is not active yet",
+            activates_at.to_rfc3339(),
+            model.prelaunch_url.clone(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_errors_keep_the_snake_case_detail() {
+        let err = parse_timestamp(Some("tomorrow".to_string()), "expiresAt").unwrap_err();
+        let AppError::InvalidInput { detail, .. } = err else {
+            panic!("expected invalid input, got {err:?}");
+        };
+        assert_eq!(detail, "expires_at is invalid");
+        assert_eq!(snake_case("activatesAt"), "activates_at");
+        assert_eq!(snake_case("from"), "from");
+    }
+}
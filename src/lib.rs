@@ -0,0 +1,17 @@
+pub mod app;
+pub mod codegen;
+pub mod common;
+pub mod config;
+pub mod db;
+pub mod handlers;
+pub mod middleware;
+pub mod models;
+pub mod problem;
+pub mod routes;
+pub mod server;
+pub mod services;
+pub mod utils;
+pub mod views;
+
+pub use codegen::CodeGenerator;
+pub use services::short_url_service::ShortUrlService;
@@ -0,0 +1,195 @@
+use std::marker::PhantomData;
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderName},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    app::AppState,
+    common::{error::AppError, scope::Scope},
+    services::api_key_service::Principal,
+};
+
+const X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+// Rejects unauthenticated requests up front; handlers still check scopes via `Authorized`.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = api_key(request.headers()).ok_or_else(|| AppError::unauthorized("api key is required"))?;
+    let principal = authenticate(&state, key).await?;
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+pub trait RequiredScope {
+    const SCOPE: Scope;
+}
+
+pub mod scopes {
+    use super::RequiredScope;
+    use crate::common::scope::Scope;
+
+    pub struct LinksCreate;
+    pub struct LinksRead;
+    pub struct LinksWrite;
+    pub struct StatsRead;
+    pub struct Admin;
+
+    impl RequiredScope for LinksCreate {
+        const SCOPE: Scope = Scope::LinksCreate;
+    }
+    impl RequiredScope for LinksRead {
+        const SCOPE: Scope = Scope::LinksRead;
+    }
+    impl RequiredScope for LinksWrite {
+        const SCOPE: Scope = Scope::LinksWrite;
+    }
+    impl RequiredScope for StatsRead {
+        const SCOPE: Scope = Scope::StatsRead;
+    }
+    impl RequiredScope for Admin {
+        const SCOPE: Scope = Scope::Admin;
+    }
+}
+
+// A principal holding scope `S`; missing credentials are 401, a missing scope is 403.
+pub struct Authorized<S> {
+    pub principal: Principal,
+    scope: PhantomData<S>,
+}
+
+impl<S: RequiredScope> Authorized<S> {
+    fn check(principal: Principal) -> Result<Self, AppError> {
+        principal.require_scope(S::SCOPE)?;
+        Ok(Self {
+            principal,
+            scope: PhantomData,
+        })
+    }
+}
+
+impl<S: RequiredScope + Send> FromRequestParts<AppState> for Authorized<S> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let principal = <Principal as FromRequestParts<AppState>>::from_request_parts(parts, state).await?;
+        Self::check(principal)
+    }
+}
+
+impl<S: RequiredScope + Send> OptionalFromRequestParts<AppState> for Authorized<S> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Option<Self>, Self::Rejection> {
+        <Principal as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state)
+            .await?
+            .map(Self::check)
+            .transpose()
+    }
+}
+
+impl FromRequestParts<AppState> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        <Principal as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state)
+            .await?
+            .ok_or_else(|| AppError::unauthorized("api key is required"))
+    }
+}
+
+impl OptionalFromRequestParts<AppState> for Principal {
+    type Rejection = AppError;
+
+    // A missing key is anonymous; a key that is present must be valid.
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Option<Self>, Self::Rejection> {
+        if let Some(principal) = parts.extensions.get::<Principal>() {
+            return Ok(Some(principal.clone()));
+        }
+        let Some(key) = api_key(&parts.headers) else {
+            return Ok(None);
+        };
+
+        let principal = authenticate(state, key).await?;
+        parts.extensions.insert(principal.clone());
+        Ok(Some(principal))
+    }
+}
+
+// API keys never contain dots, so a three-part credential is treated as a JWT.
+async fn authenticate(state: &AppState, credential: &str) -> Result<Principal, AppError> {
+    match &state.jwt_service {
+        Some(jwt_service) if credential.split('.').count() == 3 => jwt_service.authenticate(credential).await,
+        _ => state.api_key_service.authenticate(credential).await,
+    }
+}
+
+// Accepts `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+pub fn api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let key = bearer.or_else(|| headers.get(X_API_KEY).and_then(|value| value.to_str().ok()))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn parts(headers: &[(&str, &str)]) -> Parts {
+        let mut request = Request::builder().uri("/admin/short-urls");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    async fn authorize<S: RequiredScope + Send>(parts: &mut Parts) -> Result<Authorized<S>, AppError> {
+        <Authorized<S> as FromRequestParts<AppState>>::from_request_parts(parts, &AppState::for_tests()).await
+    }
+
+    #[tokio::test]
+    async fn missing_credentials_are_unauthorized() {
+        let result = authorize::<scopes::LinksRead>(&mut parts(&[])).await;
+        assert!(matches!(result, Err(AppError::Unauthorized { .. })));
+
+        let blank = authorize::<scopes::LinksRead>(&mut parts(&[("authorization", "Bearer  ")])).await;
+        assert!(matches!(blank, Err(AppError::Unauthorized { .. })));
+    }
+
+    #[tokio::test]
+    async fn missing_scopes_are_forbidden() {
+        let mut parts = parts(&[]);
+        parts.extensions.insert(Principal {
+            key_id: Some(1),
+            user_id: None,
+            scopes: vec![Scope::StatsRead],
+        });
+        assert!(authorize::<scopes::StatsRead>(&mut parts).await.is_ok());
+        let result = authorize::<scopes::LinksCreate>(&mut parts).await;
+        assert!(matches!(result, Err(AppError::Forbidden { .. })));
+    }
+
+    #[tokio::test]
+    async fn bootstrap_key_is_admin() {
+        for header in [("authorization", "Bearer boot"), ("x-api-key", "boot")] {
+            let authorized = authorize::<scopes::Admin>(&mut parts(&[header])).await.unwrap();
+            assert!(authorized.principal.is_admin());
+        }
+    }
+}
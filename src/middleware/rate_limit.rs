@@ -0,0 +1,105 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app::AppState,
+    common::{client_info::ClientInfo, error::AppError},
+    middleware::auth,
+    services::rate_limiter::RateLimiter,
+};
+
+pub async fn limit_create(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = credential_key(request.headers());
+    limit(state.create_limiter.clone(), key, &state, request, next).await
+}
+
+pub async fn limit_redirect(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    // Redirects never look at credentials, so presenting one must not buy a fresh bucket.
+    limit(state.redirect_limiter.clone(), None, &state, request, next).await
+}
+
+async fn limit(
+    limiter: Option<RateLimiter>,
+    key: Option<String>,
+    state: &AppState,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(limiter) = limiter else {
+        return Ok(next.run(request).await);
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let key = match key {
+        Some(key) => key,
+        None => ip_key(&mut parts, state).await,
+    };
+    if let Err(retry_after_secs) = limiter.acquire(&key) {
+        return Err(AppError::too_many_requests("rate limit exceeded", retry_after_secs));
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+// Credentials are not checked here, so a bucket costs no database round trip; invalid ones are
+// rejected by the handler. The key is hashed to keep secrets out of memory.
+fn credential_key(headers: &HeaderMap) -> Option<String> {
+    let credential = auth::api_key(headers)?;
+    let digest = Sha256::digest(credential.as_bytes());
+    Some(format!("cred:{:x}", digest))
+}
+
+async fn ip_key(parts: &mut Parts, state: &AppState) -> String {
+    let Ok(client) = ClientInfo::from_request_parts(parts, state).await;
+    match client.ip {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn credentials_key_buckets_without_revealing_them() {
+        let bearer = credential_key(&headers("authorization", "Bearer corto_secret")).unwrap();
+        let api_key = credential_key(&headers("x-api-key", "corto_secret")).unwrap();
+        assert_eq!(bearer, api_key);
+        assert!(bearer.starts_with("cred:"));
+        assert!(!bearer.contains("corto_secret"));
+        assert_ne!(bearer, credential_key(&headers("x-api-key", "corto_other")).unwrap());
+    }
+
+    #[test]
+    fn anonymous_requests_fall_back_to_the_client_ip() {
+        assert_eq!(credential_key(&HeaderMap::new()), None);
+        assert_eq!(credential_key(&headers("authorization", "Bearer ")), None);
+    }
+
+    #[tokio::test]
+    async fn requests_without_a_peer_share_one_bucket() {
+        let (mut parts, _) = Request::new(()).into_parts();
+        assert_eq!(ip_key(&mut parts, &AppState::for_tests()).await, "ip:unknown");
+    }
+}
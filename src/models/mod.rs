@@ -1 +1,6 @@
+pub mod api_key;
 pub mod short_url;
+pub mod short_url_schedule;
+pub mod short_url_variant;
+pub mod short_url_visit;
+pub mod user;
@@ -5,7 +5,7 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
-    pub short_code: Option<String>,
+    pub short_code: String,
     pub original_url: String,
     pub visit_count: i64,
     pub status: i16,
@@ -14,9 +14,65 @@ pub struct Model {
     pub updated_at: DateTimeWithTimeZone,
     pub deleted_at: Option<DateTimeWithTimeZone>,
     pub expires_at: Option<DateTimeWithTimeZone>,
+    pub owner_id: Option<i64>,
+    pub password_hash: Option<String>,
+    pub max_visits: Option<i64>,
+    pub activates_at: Option<DateTimeWithTimeZone>,
+    pub prelaunch_url: Option<String>,
+    pub redirect_status: Option<i16>,
+    pub title: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_term: Option<String>,
+    pub utm_content: Option<String>,
+    pub utm_override: bool,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub targeting_rules: Option<Json>,
+    pub sticky_variants: bool,
+    pub disabled_by_admin: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::short_url_visit::Entity")]
+    Visits,
+    #[sea_orm(has_many = "super::short_url_variant::Entity")]
+    Variants,
+    #[sea_orm(has_many = "super::short_url_schedule::Entity")]
+    Schedules,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::OwnerId",
+        to = "super::user::Column::Id"
+    )]
+    Owner,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Owner.def()
+    }
+}
+
+impl Related<super::short_url_visit::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Visits.def()
+    }
+}
+
+impl Related<super::short_url_variant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Variants.def()
+    }
+}
+
+impl Related<super::short_url_schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Schedules.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "short_url_variants")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub short_url_id: i64,
+    pub url: String,
+    pub weight: i32,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::short_url::Entity",
+        from = "Column::ShortUrlId",
+        to = "super::short_url::Column::Id"
+    )]
+    ShortUrl,
+}
+
+impl Related<super::short_url::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ShortUrl.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "short_url_visits")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub short_url_id: i64,
+    pub visited_at: DateTimeWithTimeZone,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub ip_address: Option<String>,
+    pub referrer_domain: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub country: Option<String>,
+    pub variant_id: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::short_url::Entity",
+        from = "Column::ShortUrlId",
+        to = "super::short_url::Column::Id"
+    )]
+    ShortUrl,
+}
+
+impl Related<super::short_url::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ShortUrl.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
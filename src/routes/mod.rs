@@ -1,16 +1,14 @@
-use axum::{routing::{get, post}, Router};
+use axum::{handler::Handler, middleware, routing::{delete, get, patch}, Router};
 use tower_http::trace::TraceLayer;
 
 use crate::{
-    handlers::{short_url_handler, admin_short_url_handler},
+    handlers::{short_url_handler, admin_short_url_handler, admin_api_key_handler, admin_user_handler},
     app::AppState,
+    middleware::{auth::require_api_key, rate_limit},
 };
 
 pub fn routes(state: AppState) -> Router {
-    Router::new()
-        .route("/api/short-urls", post(short_url_handler::create_short_url))
-        .route("/api/short-urls/{code}", get(short_url_handler::get_short_url))
-        .route("/{code}", get(short_url_handler::redirect_short_url))
+    let admin = Router::new()
         .route("/admin/short-urls", get(admin_short_url_handler::list_short_urls))
         .route(
             "/admin/short-urls/{id}",
@@ -18,6 +16,64 @@ pub fn routes(state: AppState) -> Router {
                 .patch(admin_short_url_handler::update_short_url)
                 .delete(admin_short_url_handler::delete_short_url),
         )
+        .route(
+            "/admin/short-urls/{id}/stats",
+            get(admin_short_url_handler::get_short_url_stats),
+        )
+        .route(
+            "/admin/short-urls/{id}/schedule",
+            get(admin_short_url_handler::list_schedules).post(admin_short_url_handler::create_schedule),
+        )
+        .route(
+            "/admin/short-urls/{id}/schedule/{schedule_id}",
+            patch(admin_short_url_handler::update_schedule).delete(admin_short_url_handler::delete_schedule),
+        )
+        .route(
+            "/admin/api-keys",
+            get(admin_api_key_handler::list_api_keys).post(admin_api_key_handler::create_api_key),
+        )
+        .route(
+            "/admin/api-keys/{id}",
+            delete(admin_api_key_handler::revoke_api_key),
+        )
+        .route(
+            "/admin/users",
+            get(admin_user_handler::list_users).post(admin_user_handler::create_user),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_api_key));
+
+    Router::new()
+        .route(
+            "/api/short-urls",
+            get(short_url_handler::list_own_short_urls).post(
+                short_url_handler::create_short_url
+                    .layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_create)),
+            ),
+        )
+        .route(
+            "/api/short-urls/{code}",
+            get(short_url_handler::get_short_url)
+                .patch(short_url_handler::update_own_short_url)
+                .delete(short_url_handler::delete_own_short_url),
+        )
+        .route(
+            "/api/short-urls/{code}/qr",
+            get(short_url_handler::get_short_url_qr)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_redirect)),
+        )
+        .route(
+            "/{code}",
+            get(short_url_handler::redirect_short_url)
+                .post(short_url_handler::unlock_short_url)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_redirect)),
+        )
+        .route(
+            "/{code}/{*rest}",
+            get(short_url_handler::redirect_short_url)
+                .post(short_url_handler::unlock_short_url)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_redirect)),
+        )
+        .merge(admin)
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
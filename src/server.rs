@@ -0,0 +1,169 @@
+use std::net::SocketAddr;
+use std::net::UdpSocket;
+use std::sync::Arc;
+
+use crate::app::AppState;
+use crate::codegen::CodeGenerator;
+use crate::common::redirect::validate_redirect_status;
+use crate::config::config::{AppConfig, BucketConfig, RateLimitConfig};
+use crate::services::{
+    api_key_service::ApiKeyService, cache_listener, id_allocator::IdAllocator, jwt_service::JwtService,
+    rate_limiter::RateLimiter, scheduler, short_url_cache::ShortUrlCache, short_url_service::ShortUrlService,
+    user_service::UserService, visit_recorder::VisitRecorder, visit_service::VisitService,
+};
+use crate::{db, routes};
+use axum::http::{HeaderName, StatusCode};
+use tokio::task::JoinHandle;
+
+// Runs corto until SIGINT/SIGTERM. Embedders pass their own generator instead of `codegen::from_config`.
+pub async fn serve(config: &AppConfig, code_generator: Arc<dyn CodeGenerator>) {
+    let (state, visit_worker) = build_state(config, code_generator).await;
+    let visit_recorder = state.visit_recorder.clone();
+    let app = routes::routes(state);
+
+    let socket_addr = build_socket_addr(config);
+    let listener = tokio::net::TcpListener::bind(socket_addr)
+        .await
+        .expect("failed to bind server listener");
+
+    log_server_addresses(socket_addr);
+
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .expect("server failed to run");
+
+    tracing::info!("flushing pending visits");
+    visit_recorder.shutdown();
+    if let Err(err) = visit_worker.await {
+        tracing::error!("visit recorder stopped abnormally: {}", err);
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl-c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install terminate handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+fn normalize_base_url(config: &AppConfig) -> Option<String> {
+    let base_url = config.server.base_url.as_deref().unwrap_or_default().trim();
+    if base_url.is_empty() {
+        tracing::warn!("server.base_url is not set; responses omit shortUrl and QR codes answer 503");
+        return None;
+    }
+    Some(base_url.trim_end_matches('/').to_string())
+}
+
+// For embedding corto's routes into another router; the JoinHandle is the visit recorder worker.
+pub async fn build_state(config: &AppConfig, code_generator: Arc<dyn CodeGenerator>) -> (AppState, JoinHandle<()>) {
+    let db = db::init_db(&config.datasource).await;
+    let cache = ShortUrlCache::new(&config.cache);
+    cache_listener::spawn(config.datasource.url.clone(), cache.clone());
+    let short_url_service = ShortUrlService::new(
+        db.clone(),
+        code_generator,
+        IdAllocator::new(config.short_code.id_block_size),
+        cache,
+    );
+    let visit_service = VisitService::new(db.clone());
+    let api_key_service = ApiKeyService::new(db.clone(), config.auth.bootstrap_api_key.as_deref());
+    let user_service = UserService::new(db);
+    let jwt_service = match config.auth.jwt.clone() {
+        Some(jwt) => Some(
+            JwtService::new(jwt, user_service.clone())
+                .await
+                .expect("invalid auth.jwt configuration"),
+        ),
+        None => None,
+    };
+    scheduler::spawn(short_url_service.clone(), &config.scheduler);
+    let (visit_recorder, visit_worker) =
+        VisitRecorder::spawn(short_url_service.clone(), visit_service.clone(), &config.visits);
+
+    let state = AppState {
+        short_url_service,
+        visit_service,
+        visit_recorder,
+        api_key_service,
+        user_service,
+        jwt_service,
+        create_limiter: rate_limiter(&config.rate_limit, &config.rate_limit.create),
+        redirect_limiter: rate_limiter(&config.rate_limit, &config.rate_limit.redirect),
+        // Brute-force protection stays on when the traffic budgets are disabled.
+        password_limiter: RateLimiter::new(&config.rate_limit.password, config.rate_limit.max_clients),
+        base_url: normalize_base_url(config),
+        trust_forwarded_for: config.server.trust_forwarded_for,
+        country_header: config
+            .analytics
+            .country_header
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| {
+                HeaderName::from_bytes(name.trim().as_bytes())
+                    .expect("invalid analytics.country_header")
+            }),
+        default_redirect_status: default_redirect_status(config),
+    };
+    (state, visit_worker)
+}
+
+fn rate_limiter(limits: &RateLimitConfig, budget: &BucketConfig) -> Option<RateLimiter> {
+    if !limits.enabled {
+        return None;
+    }
+    RateLimiter::new(budget, limits.max_clients)
+}
+
+fn default_redirect_status(config: &AppConfig) -> StatusCode {
+    let status = config.redirect.default_status;
+    validate_redirect_status(status, "redirect.default_status").expect("invalid redirect.default_status");
+    StatusCode::from_u16(status).expect("invalid redirect.default_status")
+}
+
+fn build_socket_addr(config: &AppConfig) -> SocketAddr {
+    let host = config.server.host.as_deref().unwrap_or("0.0.0.0");
+    let port = config.server.port;
+    format!("{}:{}", host, port)
+        .parse()
+        .expect("invalid server host or port")
+}
+
+fn log_server_addresses(socket_addr: SocketAddr) {
+    let local_ip = match get_local_ip() {
+        Some(ip) => ip,
+        None => "unknown".to_string(),
+    };
+
+    tracing::info!(
+        "server running at http://127.0.0.1:{} and http://{}:{}",
+        socket_addr.port(),
+        local_ip,
+        socket_addr.port(),
+    );
+    tracing::info!("bound on http://{}", socket_addr);
+}
+
+fn get_local_ip() -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let addr = socket.local_addr().ok()?;
+    Some(addr.ip().to_string())
+}
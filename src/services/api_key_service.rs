@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use moka::sync::Cache;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend,
+    EntityTrait, QueryFilter, QueryOrder, Set, Statement,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    common::{error::AppError, scope::{self, Scope}},
+    models::{
+        api_key::{ActiveModel, Column, Entity, Model},
+        user,
+    },
+    services::user_service::UserRole,
+    utils::base62,
+};
+
+const KEY_PREFIX: &str = "corto_";
+const KEY_SECRET_LEN: usize = 32;
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+// Only touch last_used_at once a minute per key, not on every request. The in-memory set keeps
+// busy keys off the database; the condition covers other instances.
+const TOUCH_INTERVAL: Duration = Duration::from_secs(60);
+const TOUCH_MAX_KEYS: u64 = 100_000;
+const TOUCH_SQL: &str = "UPDATE api_keys SET last_used_at = NOW() \
+    WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')";
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    db: DatabaseConnection,
+    bootstrap_key_hash: Option<String>,
+    touched: Cache<i64, ()>,
+}
+
+pub struct NewApiKey {
+    pub name: String,
+    pub user_id: Option<i64>,
+    pub scopes: Option<Vec<Scope>>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+}
+
+// The caller behind a request. The bootstrap key carries no user or key id.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub key_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Principal {
+    pub fn is_admin(&self) -> bool {
+        self.scopes.contains(&Scope::Admin)
+    }
+
+    // `admin` implies every other scope.
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.is_admin() || self.scopes.contains(&scope)
+    }
+
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            return Ok(());
+        }
+        Err(AppError::forbidden(format!("credentials are missing the {} scope", scope)))
+    }
+
+    pub fn require_user(&self) -> Result<i64, AppError> {
+        self.user_id
+            .ok_or_else(|| AppError::forbidden("api key is not bound to a user"))
+    }
+}
+
+impl ApiKeyService {
+    pub fn new(db: DatabaseConnection, bootstrap_key: Option<&str>) -> Self {
+        Self {
+            db,
+            bootstrap_key_hash: bootstrap_key
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(hash_key),
+            touched: Cache::builder()
+                .max_capacity(TOUCH_MAX_KEYS)
+                .time_to_live(TOUCH_INTERVAL)
+                .build(),
+        }
+    }
+
+    // Returns the stored row and the plaintext key, which is never persisted.
+    pub async fn create_key(&self, new_key: NewApiKey) -> Result<(Model, String), AppError> {
+        let key = format!("{}{}", KEY_PREFIX, base62::random(KEY_SECRET_LEN));
+        let active = ActiveModel {
+            user_id: Set(new_key.user_id),
+            name: Set(new_key.name),
+            key_prefix: Set(key.chars().take(DISPLAY_PREFIX_LEN).collect()),
+            key_hash: Set(hash_key(&key)),
+            scopes: Set(new_key.scopes.as_deref().map(scope::join)),
+            created_at: Set(Utc::now().fixed_offset()),
+            expires_at: Set(new_key.expires_at),
+            last_used_at: Set(None),
+            revoked_at: Set(None),
+            ..Default::default()
+        };
+
+        let model = active
+            .insert(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to create api key: {err}")))?;
+        Ok((model, key))
+    }
+
+    pub async fn list_keys(&self) -> Result<Vec<Model>, AppError> {
+        Entity::find()
+            .order_by_desc(Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to list api keys: {err}")))
+    }
+
+    pub async fn revoke_key(&self, id: i64) -> Result<(), AppError> {
+        let model = Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query api key: {err}")))?
+            .ok_or_else(|| AppError::not_found("api key not found"))?;
+        if model.revoked_at.is_some() {
+            return Ok(());
+        }
+
+        let mut active: ActiveModel = model.into();
+        active.revoked_at = Set(Some(Utc::now().fixed_offset()));
+        active
+            .update(&self.db)
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::internal(format!("failed to revoke api key: {err}")))
+    }
+
+    pub async fn authenticate(&self, key: &str) -> Result<Principal, AppError> {
+        let hash = hash_key(key);
+        if self.bootstrap_key_hash.as_deref() == Some(hash.as_str()) {
+            return Ok(Principal {
+                key_id: None,
+                user_id: None,
+                scopes: vec![Scope::Admin],
+            });
+        }
+
+        let (model, owner) = Entity::find()
+            .filter(Column::KeyHash.eq(hash))
+            .find_also_related(user::Entity)
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query api key: {err}")))?
+            .ok_or_else(|| AppError::unauthorized("api key is invalid"))?;
+        let principal = key_principal(&model, owner.as_ref(), Utc::now().fixed_offset())?;
+
+        self.touch(model.id);
+        Ok(principal)
+    }
+
+    fn touch(&self, id: i64) {
+        if !self.touched.entry(id).or_insert(()).is_fresh() {
+            return;
+        }
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            let result = db
+                .execute(Statement::from_sql_and_values(DbBackend::Postgres, TOUCH_SQL, [id.into()]))
+                .await;
+            if let Err(err) = result {
+                tracing::warn!("failed to update last use of api key {}: {}", id, err);
+            }
+        });
+    }
+}
+
+fn key_principal(
+    model: &Model,
+    owner: Option<&user::Model>,
+    now: DateTimeWithTimeZone,
+) -> Result<Principal, AppError> {
+    if model.revoked_at.is_some() {
+        return Err(AppError::forbidden("api key has been revoked"));
+    }
+    if let Some(expires_at) = model.expires_at
+        && expires_at <= now
+    {
+        return Err(AppError::unauthorized("api key has expired"));
+    }
+
+    let role = match (&model.user_id, owner) {
+        (None, _) => UserRole::Admin,
+        (Some(_), Some(owner)) => UserRole::parse(&owner.role).unwrap_or(UserRole::User),
+        (Some(_), None) => UserRole::User,
+    };
+    Ok(Principal {
+        key_id: Some(model.id),
+        user_id: model.user_id,
+        scopes: effective_scopes(model.scopes.as_deref(), role),
+    })
+}
+
+// A key never grants more than its user's role allows.
+pub fn effective_scopes(stored: Option<&str>, role: UserRole) -> Vec<Scope> {
+    let scopes = match (stored, role) {
+        (Some(stored), _) => scope::split(stored),
+        (None, UserRole::Admin) => vec![Scope::Admin],
+        (None, UserRole::User) => Scope::USER_DEFAULT.to_vec(),
+    };
+    match role {
+        UserRole::Admin => scopes,
+        UserRole::User => scopes.into_iter().filter(|scope| *scope != Scope::Admin).collect(),
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn key(user_id: Option<i64>, scopes: Option<&str>) -> Model {
+        Model {
+            id: 1,
+            user_id,
+            name: "test".to_string(),
+            key_prefix: "corto_abcdef".to_string(),
+            key_hash: hash_key("corto_abcdef"),
+            scopes: scopes.map(str::to_string),
+            created_at: Utc::now().fixed_offset(),
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn owner(role: UserRole) -> user::Model {
+        user::Model {
+            id: 7,
+            username: "owner".to_string(),
+            role: role.as_str().to_string(),
+            created_at: Utc::now().fixed_offset(),
+            external_issuer: None,
+        }
+    }
+
+    fn principal(model: &Model, owner: Option<&user::Model>) -> Result<Principal, AppError> {
+        key_principal(model, owner, Utc::now().fixed_offset())
+    }
+
+    #[test]
+    fn admin_keys_get_every_scope() {
+        let legacy = principal(&key(None, None), None).unwrap();
+        assert!(legacy.is_admin());
+        assert_eq!(legacy.user_id, None);
+
+        let admin = owner(UserRole::Admin);
+        let principal = principal(&key(Some(7), None), Some(&admin)).unwrap();
+        assert!(principal.is_admin());
+        assert!(principal.has_scope(Scope::StatsRead));
+        assert_eq!(principal.require_user().unwrap(), 7);
+    }
+
+    #[test]
+    fn user_keys_never_exceed_the_user_role() {
+        let user = owner(UserRole::User);
+        let default = principal(&key(Some(7), None), Some(&user)).unwrap();
+        assert_eq!(default.scopes, Scope::USER_DEFAULT);
+        assert!(!default.is_admin());
+
+        let escalated = principal(&key(Some(7), Some("admin links:read")), Some(&user)).unwrap();
+        assert_eq!(escalated.scopes, [Scope::LinksRead]);
+        assert!(matches!(escalated.require_scope(Scope::Admin), Err(AppError::Forbidden { .. })));
+    }
+
+    #[test]
+    fn userless_keys_with_scopes_are_not_admins() {
+        let principal = principal(&key(None, Some("stats:read")), None).unwrap();
+        assert_eq!(principal.scopes, [Scope::StatsRead]);
+        assert!(!principal.is_admin());
+        assert!(principal.require_scope(Scope::StatsRead).is_ok());
+        assert!(matches!(principal.require_scope(Scope::LinksCreate), Err(AppError::Forbidden { .. })));
+        assert!(matches!(principal.require_user(), Err(AppError::Forbidden { .. })));
+    }
+
+    #[test]
+    fn expired_and_revoked_keys_are_rejected() {
+        let now = Utc::now().fixed_offset();
+        let expired = Model {
+            expires_at: Some(now - Duration::seconds(1)),
+            ..key(None, None)
+        };
+        assert!(matches!(principal(&expired, None), Err(AppError::Unauthorized { .. })));
+
+        let valid = Model {
+            expires_at: Some(now + Duration::hours(1)),
+            ..key(None, None)
+        };
+        assert!(principal(&valid, None).is_ok());
+
+        let revoked = Model {
+            revoked_at: Some(now),
+            ..key(None, None)
+        };
+        assert!(matches!(principal(&revoked, None), Err(AppError::Forbidden { .. })));
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use sqlx::postgres::PgListener;
+use tokio::task::JoinHandle;
+
+use crate::services::short_url_cache::ShortUrlCache;
+
+pub const INVALIDATION_CHANNEL: &str = "corto_short_url_changed";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+// Evicts codes changed on other replicas. Notifications sent while the connection was
+// down are lost, so the whole cache is dropped whenever it is (re)established.
+pub fn spawn(database_url: String, cache: ShortUrlCache) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match listen(&database_url, &cache).await {
+                Ok(()) => tracing::warn!("cache invalidation listener disconnected"),
+                Err(err) => tracing::warn!("cache invalidation listener failed: {}", err),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn listen(database_url: &str, cache: &ShortUrlCache) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect(database_url).await?;
+    listener.listen(INVALIDATION_CHANNEL).await?;
+    cache.invalidate_all();
+    tracing::info!("listening for cache invalidations on {}", INVALIDATION_CHANNEL);
+
+    while let Some(notification) = listener.try_recv().await? {
+        cache.invalidate(notification.payload());
+    }
+    cache.invalidate_all();
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use tokio::sync::Mutex;
+
+use crate::common::error::AppError;
+
+const NEXT_IDS_SQL: &str = "SELECT nextval('short_urls_id_seq') AS id FROM generate_series(1, $1)";
+
+// Hands out ids reserved from the short_urls sequence in blocks, so a code can be
+// derived before the row is written. Unused ids are simply skipped on restart.
+pub struct IdAllocator {
+    block_size: i64,
+    ids: Mutex<VecDeque<i64>>,
+}
+
+impl IdAllocator {
+    pub fn new(block_size: i64) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            ids: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub async fn next_id(&self, db: &DatabaseConnection) -> Result<i64, AppError> {
+        let mut ids = self.ids.lock().await;
+        if ids.is_empty() {
+            ids.extend(self.reserve_block(db).await?);
+        }
+        ids.pop_front()
+            .ok_or_else(|| AppError::internal("id sequence returned no values"))
+    }
+
+    async fn reserve_block(&self, db: &DatabaseConnection) -> Result<Vec<i64>, AppError> {
+        let rows = db
+            .query_all(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                NEXT_IDS_SQL,
+                [self.block_size.into()],
+            ))
+            .await
+            .map_err(|err| AppError::internal(format!("failed to reserve ids: {err}")))?;
+
+        rows.iter()
+            .map(|row| {
+                row.try_get::<i64>("", "id")
+                    .map_err(|err| AppError::internal(format!("failed to reserve ids: {err}")))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    errors::ErrorKind,
+    jwk::{Jwk, JwkSet, KeyAlgorithm},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::{
+    common::{error::AppError, scope},
+    config::config::JwtConfig,
+    services::{
+        api_key_service::{effective_scopes, Principal},
+        user_service::{UserRole, UserService},
+    },
+};
+
+// An unknown `kid` triggers a refetch at most this often, so garbage tokens cannot hammer the IdP.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct JwtService {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    config: JwtConfig,
+    http: reqwest::Client,
+    user_service: UserService,
+    keys: RwLock<CachedKeys>,
+}
+
+struct CachedKeys {
+    set: JwkSet,
+    fetched_at: Option<Instant>,
+    attempted_at: Option<Instant>,
+}
+
+impl JwtService {
+    pub async fn new(config: JwtConfig, user_service: UserService) -> Result<Self, String> {
+        if config.jwks_path.is_none() && config.jwks_url.is_none() {
+            return Err("auth.jwt requires jwks_path or jwks_url".to_string());
+        }
+
+        let service = Self {
+            inner: Arc::new(Inner {
+                config,
+                http: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()
+                    .map_err(|err| format!("failed to build http client: {err}"))?,
+                user_service,
+                keys: RwLock::new(CachedKeys {
+                    set: JwkSet { keys: Vec::new() },
+                    fetched_at: None,
+                    attempted_at: None,
+                }),
+            }),
+        };
+        match (&service.inner.config.jwks_path, service.refresh().await) {
+            // A broken local file is a configuration error; a remote JWKS may come up later.
+            (Some(_), Err(err)) => return Err(err),
+            (None, Err(err)) => tracing::warn!("{}, retrying on first use", err),
+            (_, Ok(())) => {}
+        }
+        Ok(service)
+    }
+
+    pub async fn authenticate(&self, token: &str) -> Result<Principal, AppError> {
+        let config = &self.inner.config;
+        let claims = self.verify(token).await?;
+
+        let username = claim(&claims, &config.username_claim)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| {
+                AppError::unauthorized(format!("bearer token is missing the {} claim", config.username_claim))
+            })?;
+        let role = if roles(&claims, &config.roles_claim)
+            .iter()
+            .any(|role| config.admin_roles.iter().any(|admin| admin == role))
+        {
+            UserRole::Admin
+        } else {
+            UserRole::User
+        };
+        let user = self
+            .inner
+            .user_service
+            .sync_external_user(&config.issuer, username, role)
+            .await?;
+
+        // OAuth `scope` usually also holds `openid profile ...`; without any corto scope the role decides.
+        let granted = claims
+            .get("scope")
+            .and_then(Value::as_str)
+            .filter(|scope| !scope::split(scope).is_empty());
+        Ok(Principal {
+            key_id: None,
+            user_id: Some(user.id),
+            scopes: effective_scopes(granted, role),
+        })
+    }
+
+    // Signature, algorithm, issuer, audience and expiry; the claims are only trusted after this.
+    async fn verify(&self, token: &str) -> Result<Value, AppError> {
+        let config = &self.inner.config;
+        let header = decode_header(token).map_err(|_| AppError::unauthorized("bearer token is invalid"))?;
+        let jwk = self.find_key(header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| AppError::unauthorized("bearer token is invalid"))?;
+
+        // The header only picks among algorithms the key and the configuration already allow.
+        let algorithm = match jwk.common.key_algorithm {
+            Some(key_algorithm) => signing_algorithm(key_algorithm)
+                .ok_or_else(|| AppError::unauthorized("bearer token is signed with an unsupported key"))?,
+            None => header.alg,
+        };
+        if !config.algorithms.contains(&algorithm) {
+            return Err(AppError::unauthorized("bearer token uses an unsupported algorithm"));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = config.leeway_secs;
+        validation.set_issuer(&[config.issuer.as_str()]);
+        match &config.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience.as_str()]);
+                validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+            }
+            None => {
+                validation.validate_aud = false;
+                validation.set_required_spec_claims(&["exp", "iss"]);
+            }
+        }
+
+        decode::<Value>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|err| match err.kind() {
+                ErrorKind::ExpiredSignature => AppError::unauthorized("bearer token has expired"),
+                ErrorKind::InvalidIssuer => AppError::unauthorized("bearer token has an unexpected issuer"),
+                ErrorKind::InvalidAudience => AppError::unauthorized("bearer token has an unexpected audience"),
+                _ => AppError::unauthorized("bearer token is invalid"),
+            })
+    }
+
+    async fn find_key(&self, kid: Option<&str>) -> Result<Jwk, AppError> {
+        if let Some(jwk) = self.cached_key(kid, false).await {
+            return Ok(jwk);
+        }
+        if self.is_remote()
+            && self.begin_refresh().await
+            && let Err(err) = self.refresh().await
+        {
+            tracing::warn!("{}", err);
+        }
+        // Fall back to the previous keys while the JWKS endpoint is unreachable.
+        self.cached_key(kid, true)
+            .await
+            .ok_or_else(|| AppError::unauthorized("bearer token is signed with an unknown key"))
+    }
+
+    async fn cached_key(&self, kid: Option<&str>, allow_stale: bool) -> Option<Jwk> {
+        let keys = self.inner.keys.read().await;
+        let refresh_after = Duration::from_secs(self.inner.config.jwks_refresh_secs);
+        let stale = self.is_remote()
+            && keys.fetched_at.is_none_or(|fetched_at| fetched_at.elapsed() >= refresh_after);
+        if stale && !allow_stale {
+            return None;
+        }
+        match kid {
+            Some(kid) => keys.set.find(kid).cloned(),
+            // Tokens without `kid` are only accepted when there is no ambiguity.
+            None if keys.set.keys.len() == 1 => keys.set.keys.first().cloned(),
+            None => None,
+        }
+    }
+
+    fn is_remote(&self) -> bool {
+        self.inner.config.jwks_path.is_none() && self.inner.config.jwks_url.is_some()
+    }
+
+    // Claims the next refresh so concurrent requests with an unknown `kid` fetch only once.
+    async fn begin_refresh(&self) -> bool {
+        let mut keys = self.inner.keys.write().await;
+        if keys
+            .attempted_at
+            .is_some_and(|attempted_at| attempted_at.elapsed() < MIN_REFRESH_INTERVAL)
+        {
+            return false;
+        }
+        keys.attempted_at = Some(Instant::now());
+        true
+    }
+
+    async fn refresh(&self) -> Result<(), String> {
+        let set = match (&self.inner.config.jwks_path, &self.inner.config.jwks_url) {
+            (Some(path), _) => {
+                let content = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|err| format!("failed to read jwks file {}: {err}", path))?;
+                serde_json::from_str::<JwkSet>(&content)
+                    .map_err(|err| format!("failed to parse jwks file {}: {err}", path))?
+            }
+            (None, Some(url)) => self
+                .inner
+                .http
+                .get(url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| format!("failed to fetch jwks from {}: {err}", url))?
+                .json::<JwkSet>()
+                .await
+                .map_err(|err| format!("failed to parse jwks from {}: {err}", url))?,
+            (None, None) => return Ok(()),
+        };
+
+        let mut keys = self.inner.keys.write().await;
+        keys.set = set;
+        keys.fetched_at = Some(Instant::now());
+        Ok(())
+    }
+}
+
+// Encryption algorithms such as RSA-OAEP have no signing counterpart.
+fn signing_algorithm(key_algorithm: KeyAlgorithm) -> Option<Algorithm> {
+    Algorithm::from_str(&format!("{:?}", key_algorithm)).ok()
+}
+
+// Supports nested claims such as `realm_access.roles`.
+fn claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(claims, |value, segment| value.get(segment))
+}
+
+// Roles may be a JSON array or a space-separated string.
+fn roles(claims: &Value, path: &str) -> Vec<String> {
+    match claim(claims, path) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(value)) => value.split_whitespace().map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::config::default_jwt_algorithms;
+
+    const ISSUER: &str = "https://idp.test";
+    // A throwaway key; `tests/fixtures/jwks.json` holds its public half as `pinned` (alg RS256) and `open`.
+    const PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_key.pem");
+
+    async fn jwt_service(algorithms: Vec<Algorithm>) -> JwtService {
+        let config = JwtConfig {
+            jwks_path: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwks.json").to_string()),
+            jwks_url: None,
+            issuer: ISSUER.to_string(),
+            audience: Some("corto".to_string()),
+            leeway_secs: 0,
+            jwks_refresh_secs: 300,
+            username_claim: "sub".to_string(),
+            roles_claim: "roles".to_string(),
+            admin_roles: vec!["admin".to_string()],
+            algorithms,
+        };
+        JwtService::new(config, UserService::new(Default::default())).await.unwrap()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "sub": "alice",
+            "iss": ISSUER,
+            "aud": "corto",
+            "exp": chrono::Utc::now().timestamp() + 300,
+        })
+    }
+
+    fn sign(algorithm: Algorithm, kid: &str, claims: &Value) -> String {
+        let header = Header {
+            kid: Some(kid.to_string()),
+            ..Header::new(algorithm)
+        };
+        let key = match algorithm {
+            Algorithm::HS256 => EncodingKey::from_secret(PRIVATE_KEY),
+            _ => EncodingKey::from_rsa_pem(PRIVATE_KEY).unwrap(),
+        };
+        encode(&header, claims, &key).unwrap()
+    }
+
+    fn rejected(result: Result<Value, AppError>) -> bool {
+        matches!(result, Err(AppError::Unauthorized { .. }))
+    }
+
+    #[tokio::test]
+    async fn accepts_tokens_signed_as_the_key_says() {
+        let service = jwt_service(vec![Algorithm::RS256]).await;
+        let claims = service.verify(&sign(Algorithm::RS256, "pinned", &claims())).await.unwrap();
+        assert_eq!(claims["sub"], "alice");
+    }
+
+    #[tokio::test]
+    async fn rejects_an_algorithm_other_than_the_key_alg() {
+        let service = jwt_service(vec![Algorithm::RS256, Algorithm::RS512]).await;
+        assert!(rejected(service.verify(&sign(Algorithm::RS512, "pinned", &claims())).await));
+    }
+
+    #[tokio::test]
+    async fn rejects_algorithms_outside_the_allow_list() {
+        let service = jwt_service(vec![Algorithm::RS256]).await;
+        assert!(service.verify(&sign(Algorithm::RS256, "open", &claims())).await.is_ok());
+        assert!(rejected(service.verify(&sign(Algorithm::RS512, "open", &claims())).await));
+        assert!(rejected(service.verify(&sign(Algorithm::HS256, "open", &claims())).await));
+
+        // The default list has no HMAC algorithm either.
+        let service = jwt_service(default_jwt_algorithms()).await;
+        assert!(rejected(service.verify(&sign(Algorithm::HS256, "open", &claims())).await));
+    }
+
+    #[tokio::test]
+    async fn rejects_the_wrong_issuer_audience_or_expiry() {
+        let service = jwt_service(vec![Algorithm::RS256]).await;
+        let mut wrong_issuer = claims();
+        wrong_issuer["iss"] = json!("https://evil.test");
+        assert!(rejected(service.verify(&sign(Algorithm::RS256, "pinned", &wrong_issuer)).await));
+
+        let mut wrong_audience = claims();
+        wrong_audience["aud"] = json!("someone-else");
+        assert!(rejected(service.verify(&sign(Algorithm::RS256, "pinned", &wrong_audience)).await));
+
+        let mut expired = claims();
+        expired["exp"] = json!(chrono::Utc::now().timestamp() - 10);
+        assert!(rejected(service.verify(&sign(Algorithm::RS256, "pinned", &expired)).await));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_keys() {
+        let service = jwt_service(vec![Algorithm::RS256]).await;
+        assert!(rejected(service.verify(&sign(Algorithm::RS256, "missing", &claims())).await));
+    }
+}
@@ -1 +1,11 @@
+pub mod api_key_service;
+pub mod cache_listener;
+pub mod id_allocator;
+pub mod jwt_service;
+pub mod rate_limiter;
+pub mod scheduler;
+pub mod short_url_cache;
 pub mod short_url_service;
+pub mod user_service;
+pub mod visit_recorder;
+pub mod visit_service;
@@ -0,0 +1,126 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use moka::sync::Cache;
+
+use crate::config::config::BucketConfig;
+
+// Token buckets keyed by client. A bucket that sat idle long enough to refill completely
+// is indistinguishable from a new one, so it is simply evicted.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Cache<String, Arc<Mutex<Bucket>>>,
+    burst: f64,
+    refill_per_sec: f64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    // `None` when the budget is disabled with `per_minute = 0`.
+    pub fn new(config: &BucketConfig, max_clients: u64) -> Option<Self> {
+        if config.per_minute == 0 {
+            return None;
+        }
+        let burst = f64::from(config.burst.max(1));
+        let refill_per_sec = f64::from(config.per_minute) / 60.0;
+        let buckets = Cache::builder()
+            .max_capacity(max_clients)
+            .time_to_idle(Duration::from_secs_f64(burst / refill_per_sec).max(Duration::from_secs(1)))
+            .build();
+        Some(Self {
+            buckets,
+            burst,
+            refill_per_sec,
+        })
+    }
+
+    // Takes one token for `key`, or returns the seconds until one is available, rounded up.
+    pub fn acquire(&self, key: &str) -> Result<(), u64> {
+        self.take(key, 1.0)
+    }
+
+    // Like `acquire`, but leaves the token in place; for budgets that only charge failures.
+    pub fn check(&self, key: &str) -> Result<(), u64> {
+        self.take(key, 0.0)
+    }
+
+    fn take(&self, key: &str, cost: f64) -> Result<(), u64> {
+        let bucket = self.buckets.get_with_by_ref(key, || {
+            Arc::new(Mutex::new(Bucket {
+                tokens: self.burst,
+                updated_at: Instant::now(),
+            }))
+        });
+        let mut bucket = bucket.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= cost;
+            return Ok(());
+        }
+        let wait = (1.0 - bucket.tokens) / self.refill_per_sec;
+        Err((wait.ceil() as u64).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    fn limiter(burst: u32, per_minute: u32) -> RateLimiter {
+        RateLimiter::new(&BucketConfig { burst, per_minute }, 100).unwrap()
+    }
+
+    #[test]
+    fn allows_a_burst_then_reports_the_wait() {
+        let limiter = limiter(3, 6);
+        for _ in 0..3 {
+            assert!(limiter.acquire("ip:10.0.0.1").is_ok());
+        }
+        assert_eq!(limiter.acquire("ip:10.0.0.1"), Err(10));
+        // Other clients have their own bucket.
+        assert!(limiter.acquire("ip:10.0.0.2").is_ok());
+    }
+
+    #[test]
+    fn refills_over_time_up_to_the_burst() {
+        let limiter = limiter(2, 1_200);
+        assert!(limiter.acquire("key").is_ok());
+        assert!(limiter.acquire("key").is_ok());
+        assert!(limiter.acquire("key").is_err());
+
+        // 20 tokens per second: 150ms refills at least one, and never more than the burst.
+        sleep(Duration::from_millis(150));
+        assert!(limiter.acquire("key").is_ok());
+        sleep(Duration::from_millis(500));
+        assert!(limiter.acquire("key").is_ok());
+        assert!(limiter.acquire("key").is_ok());
+        assert!(limiter.acquire("key").is_err());
+    }
+
+    #[test]
+    fn check_does_not_take_a_token() {
+        let limiter = limiter(1, 1);
+        assert!(limiter.check("key").is_ok());
+        assert!(limiter.check("key").is_ok());
+        assert!(limiter.acquire("key").is_ok());
+        assert!(limiter.check("key").is_err());
+    }
+
+    #[test]
+    fn zero_per_minute_disables_the_budget() {
+        assert!(RateLimiter::new(&BucketConfig { burst: 5, per_minute: 0 }, 100).is_none());
+    }
+}
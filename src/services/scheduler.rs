@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::{config::config::SchedulerConfig, services::short_url_service::ShortUrlService};
+
+// Applies scheduled destination changes in the background. `interval_secs = 0` disables it.
+pub fn spawn(short_url_service: ShortUrlService, config: &SchedulerConfig) -> Option<JoinHandle<()>> {
+    if config.interval_secs == 0 {
+        return None;
+    }
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    Some(tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            match short_url_service.apply_due_schedules().await {
+                Ok(0) => {}
+                Ok(applied) => tracing::info!("applied scheduled destination changes to {} short urls", applied),
+                Err(err) => tracing::warn!("failed to apply scheduled destination changes: {:?}", err),
+            }
+        }
+    }))
+}
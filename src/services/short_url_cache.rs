@@ -0,0 +1,156 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use moka::{sync::Cache, Expiry};
+
+use crate::{
+    config::config::CacheConfig,
+    models::{short_url::Model, short_url_variant},
+};
+
+// Code lookups for the redirect path. `None` records a code that is unknown or not
+// active, so repeated misses do not reach the database either.
+#[derive(Clone)]
+pub struct ShortUrlCache {
+    entries: Cache<String, Option<Model>>,
+    // Variants of the same codes, evicted together with the entry.
+    variants: Cache<String, Arc<Vec<short_url_variant::Model>>>,
+    // Bumped before every invalidation. A fill that started under an older generation may
+    // hold a row read before the write, so it is dropped again instead of living for the TTL.
+    generation: Arc<AtomicU64>,
+}
+
+struct EntryExpiry {
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl EntryExpiry {
+    fn ttl_for(&self, value: &Option<Model>) -> Duration {
+        match value {
+            Some(_) => self.ttl,
+            None => self.negative_ttl,
+        }
+    }
+}
+
+impl Expiry<String, Option<Model>> for EntryExpiry {
+    fn expire_after_create(&self, _key: &String, value: &Option<Model>, _created_at: Instant) -> Option<Duration> {
+        Some(self.ttl_for(value))
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        value: &Option<Model>,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(self.ttl_for(value))
+    }
+}
+
+impl ShortUrlCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let entries = Cache::builder()
+            .max_capacity(config.max_capacity)
+            .expire_after(EntryExpiry {
+                ttl: Duration::from_secs(config.ttl_secs),
+                negative_ttl: Duration::from_secs(config.negative_ttl_secs),
+            })
+            .build();
+        let variants = Cache::builder()
+            .max_capacity(config.max_capacity)
+            .time_to_live(Duration::from_secs(config.ttl_secs))
+            .build();
+        Self {
+            entries,
+            variants,
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // Read before querying the database and pass to `insert`/`insert_variants`.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn get(&self, code: &str) -> Option<Option<Model>> {
+        self.entries.get(code)
+    }
+
+    pub fn insert(&self, code: &str, model: Option<Model>, generation: u64) {
+        self.entries.insert(code.to_string(), model);
+        if self.generation() != generation {
+            self.entries.invalidate(code);
+        }
+    }
+
+    pub fn get_variants(&self, code: &str) -> Option<Arc<Vec<short_url_variant::Model>>> {
+        self.variants.get(code)
+    }
+
+    pub fn insert_variants(&self, code: &str, variants: Arc<Vec<short_url_variant::Model>>, generation: u64) {
+        self.variants.insert(code.to_string(), variants);
+        if self.generation() != generation {
+            self.variants.invalidate(code);
+        }
+    }
+
+    pub fn invalidate(&self, code: &str) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.entries.invalidate(code);
+        self.variants.invalidate(code);
+    }
+
+    pub fn invalidate_all(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.entries.invalidate_all();
+        self.variants.invalidate_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> ShortUrlCache {
+        ShortUrlCache::new(&CacheConfig {
+            max_capacity: 100,
+            ttl_secs: 60,
+            negative_ttl_secs: 5,
+        })
+    }
+
+    #[test]
+    fn fills_are_kept_when_nothing_was_invalidated() {
+        let cache = cache();
+        let generation = cache.generation();
+        cache.insert("abc", None, generation);
+        cache.insert_variants("abc", Arc::new(Vec::new()), generation);
+        assert_eq!(cache.get("abc"), Some(None));
+        assert!(cache.get_variants("abc").is_some());
+    }
+
+    #[test]
+    fn fills_started_before_an_invalidation_are_dropped() {
+        let cache = cache();
+        let generation = cache.generation();
+        // A write lands while the lookup is still reading the old row.
+        cache.invalidate("abc");
+        cache.insert("abc", None, generation);
+        cache.insert_variants("abc", Arc::new(Vec::new()), generation);
+        assert_eq!(cache.get("abc"), None);
+        assert!(cache.get_variants("abc").is_none());
+
+        let generation = cache.generation();
+        cache.invalidate_all();
+        cache.insert("abc", None, generation);
+        assert_eq!(cache.get("abc"), None);
+    }
+}
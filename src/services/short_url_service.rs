@@ -1,65 +1,245 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set, QueryOrder, QuerySelect, PaginatorTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, EntityTrait, FromQueryResult,
+    QueryFilter, Set, QueryOrder, QuerySelect, PaginatorTrait, SqlErr, Statement, TransactionTrait, Value,
 };
-use sea_orm::prelude::{DateTimeWithTimeZone, Expr};
+use sea_orm::prelude::DateTimeWithTimeZone;
 use chrono::Utc;
 
 use crate::{
-    common::error::AppError,
+    common::error::{AppError, ValidationErrors},
+    common::targeting::{self, TargetingRule},
+    common::validation::validate_activation_window,
+    codegen::CodeGenerator,
     models::short_url::{ActiveModel, Column, Entity, Model},
-    utils::base62,
+    models::{short_url_schedule, short_url_variant},
+    services::{
+        cache_listener::INVALIDATION_CHANNEL, id_allocator::IdAllocator, short_url_cache::ShortUrlCache,
+        visit_service::Visit,
+    },
 };
 
 const STATUS_ACTIVE: i16 = 1;
 const STATUS_DISABLED: i16 = 0;
 const NOT_DELETED: i16 = 0;
 
+pub struct NewShortUrl {
+    pub original_url: String,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub alias: Option<String>,
+    pub owner_id: Option<i64>,
+    pub password_hash: Option<String>,
+    pub max_visits: Option<i64>,
+    pub activates_at: Option<DateTimeWithTimeZone>,
+    pub prelaunch_url: Option<String>,
+    pub redirect_status: Option<i16>,
+    pub title: Option<String>,
+    pub forward_query: bool,
+    pub forward_path: bool,
+    pub utm: UtmChanges,
+    pub targeting: Vec<TargetingRule>,
+    pub variants: Vec<VariantInput>,
+    pub sticky_variants: bool,
+}
+
+// Fields left as `None` are not touched; the inner `None` of nullable fields clears them.
+#[derive(Default)]
+pub struct ShortUrlChanges {
+    pub original_url: Option<String>,
+    pub status: Option<i16>,
+    // Set by admin updates only; owners may not re-enable a link while it is set.
+    pub disabled_by_admin: Option<bool>,
+    pub is_deleted: Option<i16>,
+    pub expires_at: Option<Option<DateTimeWithTimeZone>>,
+    pub password_hash: Option<Option<String>>,
+    pub max_visits: Option<Option<i64>>,
+    pub activates_at: Option<Option<DateTimeWithTimeZone>>,
+    pub prelaunch_url: Option<Option<String>>,
+    pub redirect_status: Option<Option<i16>>,
+    pub title: Option<Option<String>>,
+    pub forward_query: Option<bool>,
+    pub forward_path: Option<bool>,
+    pub utm: UtmChanges,
+    pub targeting: Option<Vec<TargetingRule>>,
+    // Replaces the whole list; see `replace_variants`.
+    pub variants: Option<Vec<VariantInput>>,
+    pub sticky_variants: Option<bool>,
+}
+
+// Only pending schedules can be changed.
+#[derive(Default)]
+pub struct ScheduleChanges {
+    pub original_url: Option<String>,
+    pub applies_at: Option<DateTimeWithTimeZone>,
+}
+
+// Moves each link with due schedules to its latest due URL and marks those schedules as applied,
+// older ones as superseded. Locking the link rather than single schedule rows keeps one link's due
+// rows in the same batch, and `SKIP LOCKED` lets several instances run the scheduler at once.
+const APPLY_DUE_SCHEDULES_SQL: &str = "\
+    WITH links AS ( \
+        SELECT u.id FROM short_urls AS u \
+        WHERE EXISTS ( \
+            SELECT 1 FROM short_url_schedules AS s \
+            WHERE s.short_url_id = u.id AND s.applied_at IS NULL AND s.applies_at <= NOW() \
+        ) \
+        ORDER BY u.id LIMIT 500 FOR UPDATE OF u SKIP LOCKED \
+    ), due AS ( \
+        SELECT s.id, s.short_url_id, s.original_url, s.applies_at FROM short_url_schedules AS s \
+        JOIN links ON links.id = s.short_url_id \
+        WHERE s.applied_at IS NULL AND s.applies_at <= NOW() \
+    ), latest AS ( \
+        SELECT DISTINCT ON (short_url_id) id, short_url_id, original_url FROM due \
+        ORDER BY short_url_id, applies_at DESC, id DESC \
+    ), marked AS ( \
+        UPDATE short_url_schedules AS s \
+        SET applied_at = NOW(), superseded = NOT EXISTS (SELECT 1 FROM latest WHERE latest.id = s.id) \
+        FROM due WHERE s.id = due.id \
+    ) \
+    UPDATE short_urls AS u SET original_url = latest.original_url, updated_at = NOW() \
+    FROM latest WHERE u.id = latest.short_url_id \
+    RETURNING u.short_code";
+
+#[derive(Debug, FromQueryResult)]
+struct AppliedRow {
+    short_code: String,
+}
+
+// `id` refers to an existing variant of the link, which keeps it and its recorded visits.
+pub struct VariantInput {
+    pub id: Option<i64>,
+    pub url: String,
+    pub weight: i32,
+}
+
+// Same convention as `ShortUrlChanges`; on create `None` simply means unset.
+#[derive(Default)]
+pub struct UtmChanges {
+    pub source: Option<Option<String>>,
+    pub medium: Option<Option<String>>,
+    pub campaign: Option<Option<String>>,
+    pub term: Option<Option<String>>,
+    pub content: Option<Option<String>>,
+    pub override_existing: Option<bool>,
+}
+
 #[derive(Clone)]
 pub struct ShortUrlService {
     db: DatabaseConnection,
+    code_generator: Arc<dyn CodeGenerator>,
+    id_allocator: Arc<IdAllocator>,
+    cache: ShortUrlCache,
 }
 
 impl ShortUrlService {
-    pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+    pub fn new(
+        db: DatabaseConnection,
+        code_generator: Arc<dyn CodeGenerator>,
+        id_allocator: IdAllocator,
+        cache: ShortUrlCache,
+    ) -> Self {
+        Self {
+            db,
+            code_generator,
+            id_allocator: Arc::new(id_allocator),
+            cache,
+        }
     }
 
-    pub async fn create_short_url(
-        &self,
-        original_url: String,
-        expires_at: Option<DateTimeWithTimeZone>,
-    ) -> Result<Model, AppError> {
-        let active = ActiveModel {
-            original_url: Set(original_url),
-            status: Set(STATUS_ACTIVE),
-            is_deleted: Set(NOT_DELETED),
-            visit_count: Set(0),
-            expires_at: Set(expires_at),
-            ..Default::default()
+    pub async fn create_short_url(&self, mut new: NewShortUrl) -> Result<Model, AppError> {
+        let variants = std::mem::take(&mut new.variants);
+        let txn = self.db.begin().await.map_err(create_failed)?;
+        let inserted = match new.alias.take() {
+            Some(alias) => self.create_with_alias(&txn, new, alias).await?,
+            None => self.create_with_generated_code(&txn, new).await?,
         };
+        if !variants.is_empty() {
+            replace_variants(&txn, inserted.id, variants).await?;
+        }
+        txn.commit().await.map_err(create_failed)?;
+        // Other instances may hold a cached miss for a chosen alias.
+        self.evict(&inserted);
+        self.broadcast_eviction(&inserted).await;
+        Ok(inserted)
+    }
 
-        let inserted = active
-            .insert(&self.db)
-            .await
-            .map_err(|err| AppError::internal(format!("failed to create short url: {err}")))?;
+    async fn create_with_generated_code(
+        &self,
+        txn: &DatabaseTransaction,
+        new: NewShortUrl,
+    ) -> Result<Model, AppError> {
+        let mut attempts = self.code_generator.max_attempts();
+        loop {
+            let id = self.id_allocator.next_id(&self.db).await?;
+            let active = ActiveModel {
+                id: Set(id),
+                short_code: Set(self.code_generator.generate(id)),
+                ..new_active_model(&new)
+            };
 
-        let code = base62::encode(inserted.id);
+            // A failed statement aborts the transaction, so each attempt runs in its own savepoint.
+            let savepoint = txn.begin().await.map_err(create_failed)?;
+            match active.insert(&savepoint).await {
+                Ok(inserted) => {
+                    savepoint.commit().await.map_err(create_failed)?;
+                    return Ok(inserted);
+                }
+                Err(err) if attempts > 1 && is_unique_violation(&err) => attempts -= 1,
+                Err(err) => return Err(create_failed(err)),
+            }
+        }
+    }
 
-        let updated = ActiveModel {
-            id: Set(inserted.id),
-            short_code: Set(Some(code)),
-            ..Default::default()
-        };
+    async fn create_with_alias(
+        &self,
+        txn: &DatabaseTransaction,
+        new: NewShortUrl,
+        alias: String,
+    ) -> Result<Model, AppError> {
+        if self.code_generator.reserves(&alias) {
+            return Err(AppError::bad_request_with_errors(
+                "alias is reserved",
+                ValidationErrors::single(
+                    "alias",
+                    "RESERVED",
+                    "alias may collide with a generated short code",
+                ),
+            ));
+        }
 
-        let saved = updated
-            .update(&self.db)
+        let existing = Entity::find()
+            .filter(Column::ShortCode.eq(alias.as_str()))
+            .one(txn)
             .await
-            .map_err(|err| AppError::internal(format!("failed to update short url: {err}")))?;
+            .map_err(|err| AppError::internal(format!("failed to query short url: {err}")))?;
+        if existing.is_some() {
+            return Err(alias_taken(&alias));
+        }
 
-        Ok(saved)
+        let active = ActiveModel {
+            short_code: Set(alias.clone()),
+            ..new_active_model(&new)
+        };
+
+        active.insert(txn).await.map_err(|err| {
+            if is_unique_violation(&err) {
+                alias_taken(&alias)
+            } else {
+                create_failed(err)
+            }
+        })
     }
 
     pub async fn find_by_code(&self, code: &str) -> Result<Model, AppError> {
+        if let Some(cached) = self.cache.get(code) {
+            return cached.ok_or_else(|| AppError::not_found("short url not found"));
+        }
+
+        let generation = self.cache.generation();
         let model = Entity::find()
             .filter(Column::ShortCode.eq(code))
             .filter(Column::IsDeleted.eq(NOT_DELETED))
@@ -68,22 +248,91 @@ impl ShortUrlService {
             .await
             .map_err(|err| AppError::internal(format!("failed to query short url: {err}")))?;
 
+        self.cache.insert(code, model.clone(), generation);
         model.ok_or_else(|| AppError::not_found("short url not found"))
     }
 
-    pub async fn increment_visit_count(&self, id: i64) -> Result<(), AppError> {
-        let result = Entity::update_many()
-            .col_expr(Column::VisitCount, Expr::col(Column::VisitCount).add(1))
-            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().fixed_offset()))
-            .filter(Column::Id.eq(id))
-            .exec(&self.db)
+    // Cached next to the link so the redirect path does not query variants on every click.
+    pub async fn find_variants(&self, model: &Model) -> Result<Arc<Vec<short_url_variant::Model>>, AppError> {
+        let code = model.short_code.as_str();
+        if let Some(cached) = self.cache.get_variants(code) {
+            return Ok(cached);
+        }
+
+        let generation = self.cache.generation();
+        let variants = Arc::new(self.list_variants(&[model.id]).await?.remove(&model.id).unwrap_or_default());
+        self.cache.insert_variants(code, variants.clone(), generation);
+        Ok(variants)
+    }
+
+    pub async fn list_variants(
+        &self,
+        short_url_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<short_url_variant::Model>>, AppError> {
+        if short_url_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = short_url_variant::Entity::find()
+            .filter(short_url_variant::Column::ShortUrlId.is_in(short_url_ids.iter().copied()))
+            .order_by_asc(short_url_variant::Column::Id)
+            .all(&self.db)
             .await
-            .map_err(|err| AppError::internal(format!("failed to update visit count: {err}")))?;
+            .map_err(|err| AppError::internal(format!("failed to query variants: {err}")))?;
 
-        if result.rows_affected == 0 {
-            return Err(AppError::not_found("short url not found"));
+        let mut grouped: HashMap<i64, Vec<short_url_variant::Model>> = HashMap::new();
+        for row in rows {
+            grouped.entry(row.short_url_id).or_default().push(row);
         }
+        Ok(grouped)
+    }
+
+    pub async fn add_visit_counts(&self, visits: &[Visit]) -> Result<(), AppError> {
+        let mut counts: BTreeMap<i64, i64> = BTreeMap::new();
+        for visit in visits.iter().filter(|visit| !visit.counted) {
+            *counts.entry(visit.short_url_id).or_default() += 1;
+        }
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let rows = (0..counts.len())
+            .map(|idx| format!("(${}::bigint, ${}::bigint)", idx * 2 + 1, idx * 2 + 2))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "UPDATE short_urls AS s SET visit_count = s.visit_count + v.visits, updated_at = NOW() \
+             FROM (VALUES {rows}) AS v(id, visits) WHERE s.id = v.id"
+        );
+        let values = counts
+            .into_iter()
+            .flat_map(|(id, visits)| [id.into(), visits.into()])
+            .collect::<Vec<Value>>();
 
+        self.db
+            .execute(Statement::from_sql_and_values(DbBackend::Postgres, sql, values))
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::internal(format!("failed to update visit count: {err}")))
+    }
+
+    // Counts a visit to a link with `max_visits` synchronously. The conditional update makes
+    // concurrent clicks on the last remaining visit race in the database, where only one wins.
+    pub async fn claim_visit(&self, id: i64) -> Result<(), AppError> {
+        let result = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "UPDATE short_urls SET visit_count = visit_count + 1, updated_at = NOW() \
+                 WHERE id = $1 AND (max_visits IS NULL OR visit_count < max_visits)",
+                [id.into()],
+            ))
+            .await
+            .map_err(|err| AppError::internal(format!("failed to update visit count: {err}")))?;
+
+        if result.rows_affected() == 0 {
+            return Err(visit_limit_reached());
+        }
         Ok(())
     }
 
@@ -96,15 +345,36 @@ impl ShortUrlService {
         model.ok_or_else(|| AppError::not_found("short url not found"))
     }
 
+    // Looks a code up for its owner, including disabled links but not deleted ones.
+    pub async fn find_owned_by_code(&self, code: &str, owner_id: i64) -> Result<Model, AppError> {
+        let model = Entity::find()
+            .filter(Column::ShortCode.eq(code))
+            .filter(Column::IsDeleted.eq(NOT_DELETED))
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query short url: {err}")))?
+            .ok_or_else(|| AppError::not_found("short url not found"))?;
+
+        if model.owner_id != Some(owner_id) {
+            return Err(AppError::forbidden("short url belongs to another user"));
+        }
+        Ok(model)
+    }
+
     pub async fn list_short_urls(
         &self,
         limit: u64,
         offset: u64,
         status: Option<i16>,
         is_deleted: Option<i16>,
+        owner_id: Option<i64>,
     ) -> Result<(u64, Vec<Model>), AppError> {
         let mut query = Entity::find();
 
+        if let Some(owner_id) = owner_id {
+            query = query.filter(Column::OwnerId.eq(owner_id));
+        }
+
         if let Some(status) = status {
             query = query.filter(Column::Status.eq(status));
         }
@@ -129,25 +399,25 @@ impl ShortUrlService {
         Ok((total, models))
     }
 
-    pub async fn update_short_url(
-        &self,
-        id: i64,
-        original_url: Option<String>,
-        status: Option<i16>,
-        is_deleted: Option<i16>,
-        expires_at: Option<Option<DateTimeWithTimeZone>>,
-    ) -> Result<Model, AppError> {
+    pub async fn update_short_url(&self, id: i64, changes: ShortUrlChanges) -> Result<Model, AppError> {
         let model = self.find_by_id(id).await?;
+        validate_activation_window(
+            changes.activates_at.unwrap_or(model.activates_at),
+            changes.expires_at.unwrap_or(model.expires_at),
+        )?;
         let mut active: ActiveModel = model.into();
         active.updated_at = Set(Utc::now().fixed_offset());
 
-        if let Some(url) = original_url {
+        if let Some(url) = changes.original_url {
             active.original_url = Set(url);
         }
-        if let Some(status) = status {
+        if let Some(status) = changes.status {
             active.status = Set(status);
         }
-        if let Some(is_deleted) = is_deleted {
+        if let Some(disabled_by_admin) = changes.disabled_by_admin {
+            active.disabled_by_admin = Set(disabled_by_admin);
+        }
+        if let Some(is_deleted) = changes.is_deleted {
             active.is_deleted = Set(is_deleted);
             active.deleted_at = if is_deleted == 1 {
                 Set(Some(Utc::now().fixed_offset()))
@@ -155,14 +425,170 @@ impl ShortUrlService {
                 Set(None)
             };
         }
-        if let Some(expires_at) = expires_at {
+        if let Some(expires_at) = changes.expires_at {
             active.expires_at = Set(expires_at);
         }
+        if let Some(password_hash) = changes.password_hash {
+            active.password_hash = Set(password_hash);
+        }
+        if let Some(max_visits) = changes.max_visits {
+            active.max_visits = Set(max_visits);
+        }
+        if let Some(activates_at) = changes.activates_at {
+            active.activates_at = Set(activates_at);
+        }
+        if let Some(prelaunch_url) = changes.prelaunch_url {
+            active.prelaunch_url = Set(prelaunch_url);
+        }
+        if let Some(redirect_status) = changes.redirect_status {
+            active.redirect_status = Set(redirect_status);
+        }
+        if let Some(title) = changes.title {
+            active.title = Set(title);
+        }
+        if let Some(forward_query) = changes.forward_query {
+            active.forward_query = Set(forward_query);
+        }
+        if let Some(forward_path) = changes.forward_path {
+            active.forward_path = Set(forward_path);
+        }
+        if let Some(sticky_variants) = changes.sticky_variants {
+            active.sticky_variants = Set(sticky_variants);
+        }
+        if let Some(targeting) = changes.targeting {
+            active.targeting_rules = Set(targeting::to_json(&targeting));
+        }
+        let utm = changes.utm;
+        if let Some(source) = utm.source {
+            active.utm_source = Set(source);
+        }
+        if let Some(medium) = utm.medium {
+            active.utm_medium = Set(medium);
+        }
+        if let Some(campaign) = utm.campaign {
+            active.utm_campaign = Set(campaign);
+        }
+        if let Some(term) = utm.term {
+            active.utm_term = Set(term);
+        }
+        if let Some(content) = utm.content {
+            active.utm_content = Set(content);
+        }
+        if let Some(override_existing) = utm.override_existing {
+            active.utm_override = Set(override_existing);
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|err| AppError::internal(format!("failed to update short url: {err}")))?;
+        if let Some(variants) = changes.variants {
+            replace_variants(&txn, id, variants).await?;
+        }
+        let updated = active
+            .update(&txn)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to update short url: {err}")))?;
+        txn.commit()
+            .await
+            .map_err(|err| AppError::internal(format!("failed to update short url: {err}")))?;
+        self.evict(&updated);
+        self.broadcast_eviction(&updated).await;
+        Ok(updated)
+    }
+
+    pub async fn list_schedules(&self, short_url_id: i64) -> Result<Vec<short_url_schedule::Model>, AppError> {
+        short_url_schedule::Entity::find()
+            .filter(short_url_schedule::Column::ShortUrlId.eq(short_url_id))
+            .order_by_asc(short_url_schedule::Column::AppliesAt)
+            .order_by_asc(short_url_schedule::Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query schedules: {err}")))
+    }
+
+    pub async fn create_schedule(
+        &self,
+        short_url_id: i64,
+        original_url: String,
+        applies_at: DateTimeWithTimeZone,
+    ) -> Result<short_url_schedule::Model, AppError> {
+        self.find_by_id(short_url_id).await?;
+        short_url_schedule::ActiveModel {
+            short_url_id: Set(short_url_id),
+            original_url: Set(original_url),
+            applies_at: Set(applies_at),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .map_err(|err| AppError::internal(format!("failed to create schedule: {err}")))
+    }
+
+    pub async fn update_schedule(
+        &self,
+        short_url_id: i64,
+        schedule_id: i64,
+        changes: ScheduleChanges,
+    ) -> Result<short_url_schedule::Model, AppError> {
+        let mut active: short_url_schedule::ActiveModel =
+            self.find_pending_schedule(short_url_id, schedule_id).await?.into();
+        if let Some(original_url) = changes.original_url {
+            active.original_url = Set(original_url);
+        }
+        if let Some(applies_at) = changes.applies_at {
+            active.applies_at = Set(applies_at);
+        }
 
         active
             .update(&self.db)
             .await
-            .map_err(|err| AppError::internal(format!("failed to update short url: {err}")))
+            .map_err(|err| AppError::internal(format!("failed to update schedule: {err}")))
+    }
+
+    pub async fn delete_schedule(&self, short_url_id: i64, schedule_id: i64) -> Result<(), AppError> {
+        let schedule = self.find_pending_schedule(short_url_id, schedule_id).await?;
+        short_url_schedule::Entity::delete_by_id(schedule.id)
+            .exec(&self.db)
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::internal(format!("failed to delete schedule: {err}")))
+    }
+
+    async fn find_pending_schedule(
+        &self,
+        short_url_id: i64,
+        schedule_id: i64,
+    ) -> Result<short_url_schedule::Model, AppError> {
+        let schedule = short_url_schedule::Entity::find_by_id(schedule_id)
+            .filter(short_url_schedule::Column::ShortUrlId.eq(short_url_id))
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query schedule: {err}")))?
+            .ok_or_else(|| AppError::not_found("schedule not found"))?;
+
+        if schedule.applied_at.is_some() {
+            return Err(AppError::conflict("schedule was already applied"));
+        }
+        Ok(schedule)
+    }
+
+    // Returns how many links changed their destination.
+    pub async fn apply_due_schedules(&self) -> Result<usize, AppError> {
+        let rows = AppliedRow::find_by_statement(Statement::from_string(
+            DbBackend::Postgres,
+            APPLY_DUE_SCHEDULES_SQL,
+        ))
+        .all(&self.db)
+        .await
+        .map_err(|err| AppError::internal(format!("failed to apply schedules: {err}")))?;
+
+        for row in &rows {
+            self.cache.invalidate(&row.short_code);
+            self.broadcast_code(&row.short_code).await;
+        }
+        Ok(rows.len())
     }
 
     pub async fn soft_delete(&self, id: i64) -> Result<(), AppError> {
@@ -173,10 +599,142 @@ impl ShortUrlService {
         active.deleted_at = Set(Some(Utc::now().fixed_offset()));
         active.updated_at = Set(Utc::now().fixed_offset());
 
-        active
+        let deleted = active
             .update(&self.db)
             .await
-            .map(|_| ())
-            .map_err(|err| AppError::internal(format!("failed to delete short url: {err}")))
+            .map_err(|err| AppError::internal(format!("failed to delete short url: {err}")))?;
+        self.evict(&deleted);
+        self.broadcast_eviction(&deleted).await;
+        Ok(())
     }
+
+    fn evict(&self, model: &Model) {
+        self.cache.invalidate(&model.short_code);
+    }
+
+    // Other replicas evict on NOTIFY; a failure here only leaves them stale until the TTL.
+    async fn broadcast_eviction(&self, model: &Model) {
+        self.broadcast_code(&model.short_code).await;
+    }
+
+    async fn broadcast_code(&self, code: &str) {
+        let result = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_notify($1, $2)",
+                [INVALIDATION_CHANNEL.into(), code.into()],
+            ))
+            .await;
+        if let Err(err) = result {
+            tracing::warn!("failed to broadcast cache eviction for {}: {}", code, err);
+        }
+    }
+}
+
+// Variants listed with an `id` are updated in place, new ones are inserted and the rest deleted,
+// so adjusting weights keeps the variant ids that analytics refer to.
+async fn replace_variants<C: ConnectionTrait>(
+    db: &C,
+    short_url_id: i64,
+    variants: Vec<VariantInput>,
+) -> Result<(), AppError> {
+    let failed = |err: DbErr| AppError::internal(format!("failed to update variants: {err}"));
+    let existing: Vec<i64> = short_url_variant::Entity::find()
+        .filter(short_url_variant::Column::ShortUrlId.eq(short_url_id))
+        .all(db)
+        .await
+        .map_err(failed)?
+        .into_iter()
+        .map(|variant| variant.id)
+        .collect();
+
+    for (index, variant) in variants.iter().enumerate() {
+        if let Some(id) = variant.id
+            && !existing.contains(&id)
+        {
+            let field = format!("variants[{}].id", index);
+            return Err(AppError::bad_request_with_errors(
+                format!("{} is invalid", field),
+                ValidationErrors::single(
+                    &field,
+                    "INVALID_VALUE",
+                    format!("variant {} does not belong to this short url", id),
+                ),
+            ));
+        }
+    }
+
+    let kept: Vec<i64> = variants.iter().filter_map(|variant| variant.id).collect();
+    short_url_variant::Entity::delete_many()
+        .filter(short_url_variant::Column::ShortUrlId.eq(short_url_id))
+        .filter(short_url_variant::Column::Id.is_not_in(kept))
+        .exec(db)
+        .await
+        .map_err(failed)?;
+
+    for variant in variants {
+        let active = short_url_variant::ActiveModel {
+            short_url_id: Set(short_url_id),
+            url: Set(variant.url),
+            weight: Set(variant.weight),
+            ..Default::default()
+        };
+        match variant.id {
+            Some(id) => {
+                short_url_variant::ActiveModel { id: Set(id), ..active }
+                    .update(db)
+                    .await
+                    .map_err(failed)?;
+            }
+            None => {
+                active.insert(db).await.map_err(failed)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn new_active_model(new: &NewShortUrl) -> ActiveModel {
+    ActiveModel {
+        original_url: Set(new.original_url.clone()),
+        status: Set(STATUS_ACTIVE),
+        is_deleted: Set(NOT_DELETED),
+        visit_count: Set(0),
+        expires_at: Set(new.expires_at),
+        owner_id: Set(new.owner_id),
+        password_hash: Set(new.password_hash.clone()),
+        max_visits: Set(new.max_visits),
+        activates_at: Set(new.activates_at),
+        prelaunch_url: Set(new.prelaunch_url.clone()),
+        redirect_status: Set(new.redirect_status),
+        title: Set(new.title.clone()),
+        forward_query: Set(new.forward_query),
+        forward_path: Set(new.forward_path),
+        utm_source: Set(new.utm.source.clone().flatten()),
+        utm_medium: Set(new.utm.medium.clone().flatten()),
+        utm_campaign: Set(new.utm.campaign.clone().flatten()),
+        utm_term: Set(new.utm.term.clone().flatten()),
+        utm_content: Set(new.utm.content.clone().flatten()),
+        utm_override: Set(new.utm.override_existing.unwrap_or(false)),
+        targeting_rules: Set(targeting::to_json(&new.targeting)),
+        sticky_variants: Set(new.sticky_variants),
+        ..Default::default()
+    }
+}
+
+pub fn visit_limit_reached() -> AppError {
+    AppError::gone("short url reached its visit limit")
+}
+
+fn create_failed(err: DbErr) -> AppError {
+    AppError::internal(format!("failed to create short url: {err}"))
+}
+
+fn alias_taken(alias: &str) -> AppError {
+    AppError::conflict(format!("alias {} is already taken", alias))
+}
+
+fn is_unique_violation(err: &DbErr) -> bool {
+    matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
 }
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, Set, SqlErr,
+};
+
+use crate::{
+    common::error::AppError,
+    models::user::{ActiveModel, Column, Entity, Model},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRole {
+    Admin,
+    User,
+}
+
+impl UserRole {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "admin" => Some(Self::Admin),
+            "user" => Some(Self::User),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::User => "user",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct UserService {
+    db: DatabaseConnection,
+}
+
+impl UserService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn create_user(&self, username: String, role: UserRole) -> Result<Model, AppError> {
+        self.insert_user(username, role, None).await
+    }
+
+    async fn insert_user(
+        &self,
+        username: String,
+        role: UserRole,
+        external_issuer: Option<&str>,
+    ) -> Result<Model, AppError> {
+        let active = ActiveModel {
+            username: Set(username.clone()),
+            role: Set(role.as_str().to_string()),
+            external_issuer: Set(external_issuer.map(str::to_string)),
+            created_at: Set(Utc::now().fixed_offset()),
+            ..Default::default()
+        };
+
+        active.insert(&self.db).await.map_err(|err: DbErr| match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                AppError::conflict(format!("username {} is already taken", username))
+            }
+            _ => AppError::internal(format!("failed to create user: {err}")),
+        })
+    }
+
+    pub async fn list_users(&self) -> Result<Vec<Model>, AppError> {
+        Entity::find()
+            .order_by_asc(Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to list users: {err}")))
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Model, AppError> {
+        let model = Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query user: {err}")))?;
+
+        model.ok_or_else(|| AppError::not_found("user not found"))
+    }
+
+    // Finds or provisions the user behind an external identity and keeps its role in sync. Only users
+    // provisioned by the same issuer are matched, so a token can never take over a local account.
+    pub async fn sync_external_user(
+        &self,
+        issuer: &str,
+        username: &str,
+        role: UserRole,
+    ) -> Result<Model, AppError> {
+        let existing = match self.find_by_username(username).await? {
+            Some(model) => model,
+            None => match self.insert_user(username.to_string(), role, Some(issuer)).await {
+                Ok(model) => return Ok(model),
+                // Another request provisioned the same user first.
+                Err(AppError::Conflict { .. }) => self
+                    .find_by_username(username)
+                    .await?
+                    .ok_or_else(|| AppError::internal("provisioned user disappeared"))?,
+                Err(err) => return Err(err),
+            },
+        };
+        ensure_managed_by(&existing, issuer)?;
+        if existing.role == role.as_str() {
+            return Ok(existing);
+        }
+
+        let mut active: ActiveModel = existing.into();
+        active.role = Set(role.as_str().to_string());
+        active
+            .update(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to update user role: {err}")))
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<Model>, AppError> {
+        Entity::find()
+            .filter(Column::Username.eq(username))
+            .one(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query user: {err}")))
+    }
+}
+
+fn ensure_managed_by(user: &Model, issuer: &str) -> Result<(), AppError> {
+    if user.external_issuer.as_deref() != Some(issuer) {
+        return Err(AppError::forbidden(format!(
+            "username {} belongs to a user not managed by this issuer",
+            user.username
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(external_issuer: Option<&str>) -> Model {
+        Model {
+            id: 1,
+            username: "alice".to_string(),
+            role: "admin".to_string(),
+            created_at: Utc::now().fixed_offset(),
+            external_issuer: external_issuer.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn external_identities_never_take_over_other_users() {
+        assert!(ensure_managed_by(&user(Some("https://idp.test")), "https://idp.test").is_ok());
+        assert!(matches!(
+            ensure_managed_by(&user(None), "https://idp.test"),
+            Err(AppError::Forbidden { .. })
+        ));
+        assert!(matches!(
+            ensure_managed_by(&user(Some("https://other.test")), "https://idp.test"),
+            Err(AppError::Forbidden { .. })
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use tokio::{
+    sync::{mpsc, Notify},
+    task::JoinHandle,
+};
+
+use crate::{
+    common::client_info::ClientInfo,
+    config::config::VisitsConfig,
+    services::{
+        short_url_service::ShortUrlService,
+        visit_service::{Visit, VisitService},
+    },
+};
+
+// Queues visits from the redirect path and writes them in batches off the request.
+#[derive(Clone)]
+pub struct VisitRecorder {
+    sender: mpsc::Sender<Visit>,
+    shutdown: Arc<Notify>,
+}
+
+impl VisitRecorder {
+    pub fn spawn(
+        short_url_service: ShortUrlService,
+        visit_service: VisitService,
+        config: &VisitsConfig,
+    ) -> (Self, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel(config.channel_capacity.max(1));
+        let shutdown = Arc::new(Notify::new());
+        let worker = Worker {
+            short_url_service,
+            visit_service,
+            receiver,
+            shutdown: shutdown.clone(),
+            batch_size: config.batch_size.max(1),
+            flush_interval: Duration::from_millis(config.flush_interval_ms.max(1)),
+        };
+
+        (Self { sender, shutdown }, tokio::spawn(worker.run()))
+    }
+
+    pub fn record(&self, short_url_id: i64, variant_id: Option<i64>, client: ClientInfo) {
+        self.enqueue(short_url_id, variant_id, client, false);
+    }
+
+    // For visits whose count was already incremented by `ShortUrlService::claim_visit`.
+    pub fn record_claimed(&self, short_url_id: i64, variant_id: Option<i64>, client: ClientInfo) {
+        self.enqueue(short_url_id, variant_id, client, true);
+    }
+
+    fn enqueue(&self, short_url_id: i64, variant_id: Option<i64>, client: ClientInfo, counted: bool) {
+        let visit = Visit {
+            short_url_id,
+            visited_at: Utc::now().fixed_offset(),
+            client,
+            variant_id,
+            counted,
+        };
+        if let Err(mpsc::error::TrySendError::Full(visit)) = self.sender.try_send(visit) {
+            tracing::warn!("visit queue is full, dropping visit for short url {}", visit.short_url_id);
+        }
+    }
+
+    // Asks the worker to drain the queue and stop; await its JoinHandle for completion.
+    pub fn shutdown(&self) {
+        self.shutdown.notify_one();
+    }
+}
+
+struct Worker {
+    short_url_service: ShortUrlService,
+    visit_service: VisitService,
+    receiver: mpsc::Receiver<Visit>,
+    shutdown: Arc<Notify>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl Worker {
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.flush_interval);
+        let mut batch = Vec::with_capacity(self.batch_size);
+
+        loop {
+            tokio::select! {
+                received = self.receiver.recv() => match received {
+                    Some(visit) => {
+                        batch.push(visit);
+                        if batch.len() >= self.batch_size {
+                            self.flush(&mut batch).await;
+                        }
+                    }
+                    None => break,
+                },
+                _ = ticker.tick() => self.flush(&mut batch).await,
+                _ = self.shutdown.notified() => {
+                    self.receiver.close();
+                    while let Some(visit) = self.receiver.recv().await {
+                        batch.push(visit);
+                        if batch.len() >= self.batch_size {
+                            self.flush(&mut batch).await;
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.flush(&mut batch).await;
+    }
+
+    async fn flush(&self, batch: &mut Vec<Visit>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let visits = std::mem::take(batch);
+        if let Err(err) = self.short_url_service.add_visit_counts(&visits).await {
+            tracing::error!("failed to flush {} visit counts: {:?}", visits.len(), err);
+        }
+        if let Err(err) = self.visit_service.record_visits(visits).await {
+            tracing::error!("failed to flush visit events: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, Utc};
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, Set,
+    Statement, Value,
+};
+
+use crate::{
+    common::{client_info::ClientInfo, error::AppError, user_agent},
+    models::short_url_visit::{ActiveModel, Entity},
+};
+
+const BREAKDOWN_LIMIT: i64 = 10;
+const UNKNOWN: &str = "(unknown)";
+const DIRECT: &str = "(direct)";
+// Visits that went to `original_url` or a targeting rule rather than a variant.
+const NO_VARIANT: &str = "(none)";
+
+#[derive(Debug, Clone)]
+pub struct Visit {
+    pub short_url_id: i64,
+    pub visited_at: DateTimeWithTimeZone,
+    pub client: ClientInfo,
+    pub variant_id: Option<i64>,
+    // Already added to `visit_count` when the visit was claimed against `max_visits`.
+    pub counted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsBucket {
+    Hour,
+    Day,
+    Week,
+}
+
+impl StatsBucket {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        match self {
+            Self::Hour => Duration::hours(1),
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::weeks(1),
+        }
+    }
+
+    // Matches Postgres date_trunc in UTC, where weeks start on Monday.
+    pub fn truncate(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Hour => time.duration_trunc(Duration::hours(1)).unwrap_or(time),
+            Self::Day => time.duration_trunc(Duration::days(1)).unwrap_or(time),
+            Self::Week => {
+                let day = Self::Day.truncate(time);
+                day - Duration::days(day.weekday().num_days_from_monday() as i64)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Dimension {
+    ReferrerDomain,
+    Browser,
+    Os,
+    Country,
+    Variant,
+}
+
+impl Dimension {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::ReferrerDomain => "referrer_domain",
+            Self::Browser => "browser",
+            Self::Os => "os",
+            Self::Country => "country",
+            Self::Variant => "variant_id::text",
+        }
+    }
+
+    fn missing_label(&self) -> &'static str {
+        match self {
+            Self::ReferrerDomain => DIRECT,
+            Self::Variant => NO_VARIANT,
+            _ => UNKNOWN,
+        }
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct BucketRow {
+    bucket_start: DateTime<Utc>,
+    clicks: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct BreakdownRow {
+    pub value: String,
+    pub clicks: i64,
+}
+
+#[derive(Debug)]
+pub struct VisitStats {
+    pub total: i64,
+    pub series: Vec<(DateTime<Utc>, i64)>,
+    pub referrers: Vec<BreakdownRow>,
+    pub browsers: Vec<BreakdownRow>,
+    pub operating_systems: Vec<BreakdownRow>,
+    pub countries: Vec<BreakdownRow>,
+    pub variants: Vec<BreakdownRow>,
+}
+
+#[derive(Clone)]
+pub struct VisitService {
+    db: DatabaseConnection,
+}
+
+impl VisitService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn record_visits(&self, visits: Vec<Visit>) -> Result<(), AppError> {
+        if visits.is_empty() {
+            return Ok(());
+        }
+
+        let models = visits.into_iter().map(|visit| {
+            let client = visit.client;
+            let agent = client
+                .user_agent
+                .as_deref()
+                .map(user_agent::parse)
+                .unwrap_or_default();
+
+            ActiveModel {
+                short_url_id: Set(visit.short_url_id),
+                visited_at: Set(visit.visited_at),
+                ip_address: Set(client.anonymized_ip()),
+                referrer_domain: Set(client.referrer_domain()),
+                browser: Set(agent.browser),
+                os: Set(agent.os),
+                referrer: Set(client.referrer),
+                user_agent: Set(client.user_agent),
+                accept_language: Set(client.accept_language),
+                country: Set(client.country),
+                variant_id: Set(visit.variant_id),
+                ..Default::default()
+            }
+        });
+
+        Entity::insert_many(models)
+            .exec(&self.db)
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::internal(format!("failed to record visits: {err}")))
+    }
+
+    pub async fn visit_stats(
+        &self,
+        short_url_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: StatsBucket,
+    ) -> Result<VisitStats, AppError> {
+        let rows = BucketRow::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT date_trunc($1, visited_at, 'UTC') AS bucket_start, COUNT(*) AS clicks \
+             FROM short_url_visits \
+             WHERE short_url_id = $2 AND visited_at >= $3 AND visited_at < $4 \
+             GROUP BY 1",
+            [
+                bucket.as_str().into(),
+                short_url_id.into(),
+                from.into(),
+                to.into(),
+            ],
+        ))
+        .all(&self.db)
+        .await
+        .map_err(|err| AppError::internal(format!("failed to query visit stats: {err}")))?;
+
+        let counts: HashMap<DateTime<Utc>, i64> = rows
+            .into_iter()
+            .map(|row| (row.bucket_start, row.clicks))
+            .collect();
+        let mut series = Vec::new();
+        let mut cursor = bucket.truncate(from);
+        while cursor < to {
+            series.push((cursor, counts.get(&cursor).copied().unwrap_or(0)));
+            cursor += bucket.step();
+        }
+
+        Ok(VisitStats {
+            total: counts.values().sum(),
+            series,
+            referrers: self.breakdown(short_url_id, from, to, Dimension::ReferrerDomain).await?,
+            browsers: self.breakdown(short_url_id, from, to, Dimension::Browser).await?,
+            operating_systems: self.breakdown(short_url_id, from, to, Dimension::Os).await?,
+            countries: self.breakdown(short_url_id, from, to, Dimension::Country).await?,
+            variants: self.breakdown(short_url_id, from, to, Dimension::Variant).await?,
+        })
+    }
+
+    async fn breakdown(
+        &self,
+        short_url_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        dimension: Dimension,
+    ) -> Result<Vec<BreakdownRow>, AppError> {
+        let sql = format!(
+            "SELECT COALESCE({column}, $1) AS value, COUNT(*) AS clicks \
+             FROM short_url_visits \
+             WHERE short_url_id = $2 AND visited_at >= $3 AND visited_at < $4 \
+             GROUP BY 1 ORDER BY clicks DESC, value LIMIT $5",
+            column = dimension.column(),
+        );
+        let values: [Value; 5] = [
+            dimension.missing_label().into(),
+            short_url_id.into(),
+            from.into(),
+            to.into(),
+            BREAKDOWN_LIMIT.into(),
+        ];
+
+        BreakdownRow::find_by_statement(Statement::from_sql_and_values(DbBackend::Postgres, sql, values))
+            .all(&self.db)
+            .await
+            .map_err(|err| AppError::internal(format!("failed to query visit stats: {err}")))
+    }
+}
@@ -1,7 +1,11 @@
 use std::sync::OnceLock;
 
+use rand::Rng;
+
 const ALPHABET: &[u8; 62] = b"hNzYdDs6xriR7elMCZIAq1BtwHjQXSOup2FEn8cJfo4Gyk0W3bPULva5K9VgmT";
 
+pub const ZERO_DIGIT: char = ALPHABET[0] as char;
+
 fn lookup_table() -> &'static [i8; 256] {
     static TABLE: OnceLock<[i8; 256]> = OnceLock::new();
     TABLE.get_or_init(|| {
@@ -51,3 +55,16 @@ pub fn decode(input: &str) -> Result<i64, String> {
 
     Ok(value)
 }
+
+pub fn is_canonical(input: &str) -> bool {
+    decode(input)
+        .map(|value| value > 0 && encode(value) == input)
+        .unwrap_or(false)
+}
+
+pub fn random(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+        .collect()
+}
@@ -1 +1,3 @@
 pub mod base62;
+pub mod permutation;
+pub mod qr;
@@ -0,0 +1,86 @@
+const HALF_BITS: u32 = 18;
+const HALF_MASK: u64 = (1 << HALF_BITS) - 1;
+const BLOCK_MASK: u64 = (1 << (HALF_BITS * 2)) - 1;
+const ROUNDS: u64 = 4;
+
+pub fn derive_key(secret: &str) -> u64 {
+    // FNV-1a, stable across builds so codes survive upgrades.
+    secret.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// Feistel network over the low 36 bits; higher bits pass through, so the
+// mapping stays a bijection on positive ids.
+pub fn permute(id: i64, key: u64) -> i64 {
+    let value = id as u64;
+    let high = value & !BLOCK_MASK;
+    let mut left = (value >> HALF_BITS) & HALF_MASK;
+    let mut right = value & HALF_MASK;
+
+    for round in 0..ROUNDS {
+        let next = left ^ round_function(right, key, round);
+        left = right;
+        right = next;
+    }
+
+    (high | (left << HALF_BITS) | right) as i64
+}
+
+fn round_function(half: u64, key: u64, round: u64) -> u64 {
+    let mut z = half ^ key.rotate_left((round * 16) as u32) ^ round.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)) & HALF_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{codegen::{CodeGenerator, ObfuscatedCodeGenerator}, utils::base62};
+
+    // Runs the rounds backwards; only a bijection can be undone for every input.
+    fn unpermute(id: i64, key: u64) -> i64 {
+        let value = id as u64;
+        let high = value & !BLOCK_MASK;
+        let mut left = (value >> HALF_BITS) & HALF_MASK;
+        let mut right = value & HALF_MASK;
+
+        for round in (0..ROUNDS).rev() {
+            let previous = right ^ round_function(left, key, round);
+            right = left;
+            left = previous;
+        }
+
+        (high | (left << HALF_BITS) | right) as i64
+    }
+
+    #[test]
+    fn permute_is_reversible() {
+        let key = derive_key("test-secret");
+        for id in (1..200_000).chain([BLOCK_MASK as i64, BLOCK_MASK as i64 + 1, i64::MAX]) {
+            let permuted = permute(id, key);
+            assert!(permuted >= 0);
+            assert_eq!(permuted as u64 & !BLOCK_MASK, id as u64 & !BLOCK_MASK);
+            assert_eq!(unpermute(permuted, key), id);
+        }
+    }
+
+    #[test]
+    fn obfuscated_codes_stay_distinct() {
+        let generator = ObfuscatedCodeGenerator::new("test-secret");
+        let codes: HashSet<String> = (10_001..110_001).map(|id| generator.generate(id)).collect();
+        assert_eq!(codes.len(), 100_000);
+        assert!(codes.iter().all(|code| generator.reserves(code)));
+        assert!(codes.iter().all(|code| !base62::is_canonical(code)));
+    }
+
+    #[test]
+    fn keys_change_the_mapping() {
+        let first = derive_key("first");
+        let second = derive_key("second");
+        assert!((1..100).any(|id| permute(id, first) != permute(id, second)));
+    }
+}
@@ -0,0 +1,122 @@
+use qrcode::{Color, EcLevel, QrCode};
+
+pub struct QrOptions {
+    // Target edge length in pixels; modules are scaled by a whole factor, so PNGs may come out smaller.
+    pub size: u32,
+    // Quiet zone in modules.
+    pub margin: u32,
+    pub ec_level: EcLevel,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+}
+
+struct Matrix {
+    width: u32,
+    dark: Vec<bool>,
+}
+
+fn encode(data: &str, ec_level: EcLevel) -> Result<Matrix, String> {
+    let code = QrCode::with_error_correction_level(data, ec_level).map_err(|err| err.to_string())?;
+    Ok(Matrix {
+        width: code.width() as u32,
+        dark: code
+            .to_colors()
+            .into_iter()
+            .map(|color| color == Color::Dark)
+            .collect(),
+    })
+}
+
+pub fn render_png(data: &str, options: &QrOptions) -> Result<Vec<u8>, String> {
+    let matrix = encode(data, options.ec_level)?;
+    let modules = matrix.width + 2 * options.margin;
+    let scale = (options.size / modules).max(1);
+    let edge = modules * scale;
+
+    let mut pixels = Vec::with_capacity((edge * edge * 3) as usize);
+    for y in 0..edge {
+        for x in 0..edge {
+            let color = if is_dark(&matrix, x / scale, y / scale, options.margin) {
+                options.foreground
+            } else {
+                options.background
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut encoder = png::Encoder::new(&mut output, edge, edge);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(&pixels).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(output)
+}
+
+pub fn render_svg(data: &str, options: &QrOptions) -> Result<String, String> {
+    let matrix = encode(data, options.ec_level)?;
+    let modules = matrix.width + 2 * options.margin;
+
+    // One path in module units; the viewBox scales it to `size` without rounding.
+    let mut path = String::new();
+    for y in 0..matrix.width {
+        for x in 0..matrix.width {
+            if matrix.dark[(y * matrix.width + x) as usize] {
+                path.push_str(&format!("M{},{}h1v1h-1z", x + options.margin, y + options.margin));
+            }
+        }
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {modules} {modules}" shape-rendering="crispEdges">
+<rect width="{modules}" height="{modules}" fill="{background}"/>
+<path d="{path}" fill="{foreground}"/>
+</svg>
+"#,
+        size = options.size,
+        modules = modules,
+        background = hex(options.background),
+        foreground = hex(options.foreground),
+        path = path,
+    ))
+}
+
+fn is_dark(matrix: &Matrix, x: u32, y: u32, margin: u32) -> bool {
+    let (Some(x), Some(y)) = (x.checked_sub(margin), y.checked_sub(margin)) else {
+        return false;
+    };
+    x < matrix.width && y < matrix.width && matrix.dark[(y * matrix.width + x) as usize]
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Accepts `#rrggbb`, `rrggbb`, `#rgb` and `rgb`.
+pub fn parse_color(input: &str) -> Option<[u8; 3]> {
+    let digits = input.trim().trim_start_matches('#');
+    if !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+    match digits.len() {
+        3 => {
+            let mut color = [0; 3];
+            for (idx, ch) in digits.chars().enumerate() {
+                let value = ch.to_digit(16)? as u8;
+                color[idx] = value * 17;
+            }
+            Some(color)
+        }
+        6 => {
+            let mut color = [0; 3];
+            for (idx, slot) in color.iter_mut().enumerate() {
+                *slot = u8::from_str_radix(&digits[idx * 2..idx * 2 + 2], 16).ok()?;
+            }
+            Some(color)
+        }
+        _ => None,
+    }
+}
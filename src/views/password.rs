@@ -0,0 +1,22 @@
+use crate::views::{escape, page};
+
+// `action` is the path (and query) the form was served from, so passthrough survives the post.
+pub fn render(action: &str, error: Option<&str>) -> String {
+    let error = error
+        .map(|error| format!(r#"<p class="error">{}</p>"#, escape(error)))
+        .unwrap_or_default();
+    page(
+        "Password required",
+        &format!(
+            r#"<h1>Password required</h1>
+<p>This link is protected. Enter the password to continue.</p>
+{error}
+<form method="post" action="{action}">
+<input type="password" name="password" autofocus required>
+<button type="submit">Continue</button>
+</form>"#,
+            error = error,
+            action = escape(action),
+        ),
+    )
+}
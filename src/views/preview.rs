@@ -0,0 +1,21 @@
+use crate::views::{escape, page};
+
+pub fn render(code: &str, title: Option<&str>, destination: &str, created_at: &str) -> String {
+    let heading = title.unwrap_or("Link preview");
+    page(
+        heading,
+        &format!(
+            r#"<h1>{heading}</h1>
+<p>This short link leads to:</p>
+<p><code>{destination}</code></p>
+<p>Created {created_at}</p>
+<form method="get" action="/{code}">
+<button type="submit">Continue</button>
+</form>"#,
+            heading = escape(heading),
+            destination = escape(destination),
+            created_at = escape(created_at),
+            code = escape(code),
+        ),
+    )
+}